@@ -0,0 +1,166 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use dnn::Posteriors;
+use error::{Error, Result};
+use fileutil;
+
+const MAGIC: &[u8; 8] = b"VRPOST01";
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// dnn posteriors of utterances saved on disk, so that later runs skip the network.
+//
+// files are stored at <dir>/<model checksum>/<utterance path hash>.post and hold
+// the magic, model checksum, utterance path, frame and label counts, then the
+// posteriors as little endian f32 in [frame][label] order.
+pub struct PosteriorCache {
+    dir: PathBuf,
+    checksum: u64,
+}
+
+impl PosteriorCache {
+    // the checksum covers every file under model_dir except priors.txt,
+    // which does not change the network outputs
+    pub fn open(dir: &str, model_dir: &str) -> Result<PosteriorCache> {
+        let checksum = model_checksum(Path::new(model_dir)).map_err(|error| Error::io(model_dir, error))?;
+        let dir = Path::new(dir).join(format!("{:016x}", checksum));
+        fs::create_dir_all(&dir).map_err(|error| Error::io(&dir.to_string_lossy(), error))?;
+
+        Ok(PosteriorCache { dir, checksum })
+    }
+
+    // None when the utterance is not cached or its file is unreadable
+    pub fn load(&self, utterance_path: &OsStr) -> Option<Posteriors> {
+        let mut bytes = Vec::new();
+        File::open(self.file_path(utterance_path)).ok()?.read_to_end(&mut bytes).ok()?;
+
+        let mut reader = ByteReader { bytes: &bytes, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC || reader.u64()? != self.checksum {
+            return None;
+        }
+        let path_len = reader.u32()? as usize;
+        if reader.take(path_len)? != path_bytes(utterance_path).as_slice() {
+            return None;
+        }
+
+        let n_frames = reader.u32()? as usize;
+        let n_labels = reader.u32()? as usize;
+        let values: Vec<f32> = reader.take(n_frames * n_labels * 4)?
+            .chunks(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        if reader.position != bytes.len() {
+            return None;
+        }
+
+        Some(Posteriors { n_labels, values })
+    }
+
+    pub fn store(&self, utterance_path: &OsStr, posteriors: &Posteriors) -> Result<()> {
+        let file_path = self.file_path(utterance_path);
+        let path = file_path.to_string_lossy();
+        let file = fileutil::create_file(&path)?;
+        self.write(&mut BufWriter::new(file), utterance_path, posteriors)
+            .map_err(|error| Error::io(&path, error))
+    }
+
+    fn write<W: Write>(&self, w: &mut W, utterance_path: &OsStr, posteriors: &Posteriors) -> io::Result<()> {
+        let path_bytes = path_bytes(utterance_path);
+        w.write_all(MAGIC)?;
+        w.write_all(&self.checksum.to_le_bytes())?;
+        w.write_all(&(path_bytes.len() as u32).to_le_bytes())?;
+        w.write_all(&path_bytes)?;
+        w.write_all(&(posteriors.n_frames() as u32).to_le_bytes())?;
+        w.write_all(&(posteriors.n_labels as u32).to_le_bytes())?;
+        for value in posteriors.values.iter() {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        w.flush()
+    }
+
+    fn file_path(&self, utterance_path: &OsStr) -> PathBuf {
+        let mut hash = Fnv::default();
+        hash.write(&path_bytes(utterance_path));
+        self.dir.join(format!("{:016x}.post", hash.0))
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(n)?;
+        let bytes = self.bytes.get(self.position..end)?;
+        self.position = end;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.take(4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let bytes = self.take(8)?;
+        let mut array = [0u8; 8];
+        array.copy_from_slice(bytes);
+        Some(u64::from_le_bytes(array))
+    }
+}
+
+// 64 bit FNV-1a
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Fnv {
+        Fnv(FNV_OFFSET)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes.iter() {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+fn path_bytes(path: &OsStr) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+fn model_checksum(model_dir: &Path) -> io::Result<u64> {
+    let mut files = Vec::new();
+    list_model_files(model_dir, model_dir, &mut files)?;
+    files.sort();
+
+    let mut hash = Fnv::default();
+    for relative_path in files.iter() {
+        hash.write(relative_path.to_string_lossy().as_bytes());
+        hash.write(&[0]);
+        let mut contents = Vec::new();
+        File::open(model_dir.join(relative_path))?.read_to_end(&mut contents)?;
+        hash.write(&contents);
+    }
+
+    Ok(hash.0)
+}
+
+fn list_model_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_model_files(root, &path, files)?;
+        } else if path.file_name() != Some(OsStr::new("priors.txt")) {
+            files.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+        }
+    }
+
+    Ok(())
+}
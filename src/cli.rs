@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+pub struct Command {
+    pub name: &'static str,
+    pub about: &'static str,
+    pub options: &'static [&'static [Opt]], // option groups shared between commands
+}
+
+impl Command {
+    fn all_options(&self) -> impl Iterator<Item = &'static Opt> {
+        self.options.iter().flat_map(|group| group.iter())
+    }
+}
+
+pub struct Opt {
+    pub name: &'static str,
+    pub value: &'static str, // placeholder of the value in usage
+    pub default: Option<&'static str>,
+    pub help: &'static str,
+}
+
+impl Opt {
+    pub const fn value(name: &'static str, value: &'static str, default: &'static str, help: &'static str) -> Opt {
+        Opt { name, value, default: Some(default), help }
+    }
+
+    pub const fn optional(name: &'static str, value: &'static str, help: &'static str) -> Opt {
+        Opt { name, value, default: None, help }
+    }
+}
+
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+pub struct Matches {
+    values: HashMap<&'static str, String>,
+}
+
+impl Matches {
+    pub fn parse(command: &Command, args: &[String]) -> Result<Matches, UsageError> {
+        let mut values = HashMap::new();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => return Err(UsageError(format!("unexpected argument '{}'", arg))),
+            };
+            let opt = command.all_options()
+                .find(|opt| opt.name == name)
+                .ok_or_else(|| UsageError(format!("unknown option '--{}' for '{}'", name, command.name)))?;
+
+            let value = args.next()
+                .ok_or_else(|| UsageError(format!("option '--{}' needs a value", name)))?;
+            values.insert(opt.name, value.clone());
+        }
+
+        for opt in command.all_options() {
+            if let Some(default) = opt.default {
+                values.entry(opt.name).or_insert_with(|| default.to_string());
+            }
+        }
+
+        Ok(Matches { values })
+    }
+
+    // value of an option with a default
+    pub fn value(&self, name: &str) -> &str {
+        self.values.get(name).map(|value| value.as_str()).expect("Option Default")
+    }
+
+    pub fn optional(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|value| value.as_str())
+    }
+
+    pub fn parse_value<T: FromStr>(&self, name: &str) -> Result<T, UsageError> {
+        let value = self.value(name);
+        value.parse()
+            .map_err(|_| UsageError(format!("invalid value '{}' for '--{}'", value, name)))
+    }
+
+    pub fn parse_optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, UsageError> {
+        match self.optional(name) {
+            Some(value) => value.parse()
+                .map(Some)
+                .map_err(|_| UsageError(format!("invalid value '{}' for '--{}'", value, name))),
+            None => Ok(None),
+        }
+    }
+
+    // comma separated values
+    pub fn parse_list<T: FromStr>(&self, name: &str) -> Result<Vec<T>, UsageError> {
+        self.value(name).split(',')
+            .map(|value| value.trim().parse()
+                .map_err(|_| UsageError(format!("invalid value '{}' in '--{}'", value, name))))
+            .collect()
+    }
+}
+
+pub fn usage(program: &str, commands: &[&Command]) -> String {
+    let mut usage = format!("usage: {} <command> [options]\n\ncommands:\n", program);
+    for command in commands.iter() {
+        usage.push_str(&format!("  {:<10} {}\n", command.name, command.about));
+    }
+    usage.push_str(&format!("\nrun '{} help <command>' for the options of a command.\n", program));
+    usage
+}
+
+pub fn command_usage(program: &str, command: &Command) -> String {
+    let mut usage = format!("usage: {} {} [options]\n{}\n\noptions:\n", program, command.name, command.about);
+    for opt in command.all_options() {
+        let name = format!("--{} <{}>", opt.name, opt.value);
+        let default = match opt.default {
+            Some(default) => format!(" [default: {}]", default),
+            None => String::new(),
+        };
+        usage.push_str(&format!("  {:<28} {}{}\n", name, opt.help, default));
+    }
+    usage
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+use cli::{Command, Matches, Opt};
+use command::{self, MODEL_OPTIONS, INPUT_OPTIONS, SCALE_OPTIONS};
+use viterbi_rust::{fileutil, dnn, mlf, Recognizer};
+use viterbi_rust::constants::PRINT_PERCENT_COUNT;
+use viterbi_rust::viterbi::Unit;
+
+pub const COMMAND: Command = Command {
+    name: "align",
+    about: "align spectrograms to their reference transcriptions and write word, phone and state times",
+    options: &[
+        MODEL_OPTIONS,
+        INPUT_OPTIONS,
+        SCALE_OPTIONS,
+        &[
+            Opt::value("reference", "file", "reference.txt", "reference MLF of the words to align"),
+            Opt::value("output", "file", "aligned.txt", "MLF of word times to write"),
+            Opt::optional("phone-output", "file", "MLF of phone times to write"),
+            Opt::optional("state-output", "file", "MLF of HMM state times to write, states named phone[n] as in label_info.txt"),
+        ],
+    ],
+};
+
+fn create_mlf(path: &str) -> command::Result<File> {
+    let mut file = fileutil::create_file(path)?;
+    mlf::write_header(&mut file)?;
+    Ok(file)
+}
+
+fn write_units(file: &mut File, name: &str, units: &[Unit]) -> command::Result {
+    let labels: Vec<mlf::Label> = units.iter().map(|unit| unit.label()).collect();
+    mlf::write_labels(file, name, &labels)?;
+    Ok(())
+}
+
+pub fn execute(matches: &Matches) -> command::Result {
+    let params = command::params(matches)?;
+    let reference: HashMap<String, Vec<String>> = mlf::read(matches.value("reference"))?.into_iter()
+        .map(|transcription| (transcription.key(), transcription.words))
+        .collect();
+    let test_file_paths = command::input_paths(matches)?;
+    let cache = command::open_cache(matches)?;
+
+    let mut word_file = create_mlf(matches.value("output"))?;
+    let mut phone_file = match matches.optional("phone-output") {
+        Some(path) => Some(create_mlf(path)?),
+        None => None,
+    };
+    let mut state_file = match matches.optional("state-output") {
+        Some(path) => Some(create_mlf(path)?),
+        None => None,
+    };
+
+    let phones = command::load_phones(matches)?;
+    let (words, _) = command::load_words(matches, &phones)?;
+    let dnn = dnn::load(matches.value("dnn"), &phones)?;
+    let mut recognizer = Recognizer::new(&words, dnn);
+    recognizer.set_params(params);
+
+    let mut n_failed = 0;
+    for (count, test_file_path) in test_file_paths.iter().enumerate() {
+        if count % PRINT_PERCENT_COUNT == 0 {
+            println!("{:.2}%..", count as f64 / test_file_paths.len() as f64 * 100f64);
+        }
+
+        let rec_name = command::get_rec_name(test_file_path)?;
+        let transcript: Vec<&str> = match reference.get(&mlf::utterance_key(&rec_name)) {
+            Some(words) => words.iter().map(|word| word.as_str()).collect(),
+            None => {
+                println!("No reference for {}", rec_name);
+                n_failed += 1;
+                continue;
+            },
+        };
+        let posteriors = command::read_posteriors(test_file_path, cache.as_ref(), |spectrogram| recognizer.compute_posteriors(spectrogram))?;
+        let posteriors = match posteriors {
+            Some(posteriors) => posteriors,
+            None => continue,
+        };
+        let alignment = match recognizer.align(&posteriors, &transcript) {
+            Ok(alignment) => alignment,
+            Err(error) => {
+                println!("Can't align {}: {}", rec_name, error);
+                n_failed += 1;
+                continue;
+            },
+        };
+
+        write_units(&mut word_file, &rec_name, &alignment.words)?;
+        if let Some(file) = phone_file.as_mut() {
+            write_units(file, &rec_name, &alignment.phones)?;
+        }
+        if let Some(file) = state_file.as_mut() {
+            write_units(file, &rec_name, &alignment.states)?;
+        }
+    }
+
+    println!("100%");
+    if n_failed > 0 {
+        println!("{} utterances not aligned", n_failed);
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::time::Instant;
+
+use cli::{Command, Matches, Opt};
+use command::{self, MODEL_OPTIONS, INPUT_OPTIONS, SCALE_OPTIONS};
+use viterbi_rust::{dnn, mlf, score, Recognizer};
+use viterbi_rust::mlf::Transcription;
+use viterbi_rust::viterbi::Pruning;
+
+pub const COMMAND: Command = Command {
+    name: "beam-report",
+    about: "decode with several beams and report the accuracy/speed trade-off",
+    options: &[
+        MODEL_OPTIONS,
+        INPUT_OPTIONS,
+        SCALE_OPTIONS,
+        &[
+            Opt::value("reference", "file", "reference.txt", "reference MLF"),
+            Opt::value("beams", "list", "50,100,200,400,inf", "comma separated beams to try"),
+            Opt::value("word-end-beams", "list", "inf", "comma separated word end beams to try"),
+            Opt::value("max-actives", "list", "0", "comma separated active state limits to try, 0 for no limit"),
+        ],
+    ],
+};
+
+pub fn execute(matches: &Matches) -> command::Result {
+    let beams: Vec<f64> = matches.parse_list("beams")?;
+    let word_end_beams: Vec<f64> = matches.parse_list("word-end-beams")?;
+    let max_actives: Vec<usize> = matches.parse_list("max-actives")?;
+    let params = command::params(matches)?;
+
+    let reference = mlf::read(matches.value("reference"))?;
+
+    let phones = command::load_phones(matches)?;
+    let (words, lm) = command::load_words(matches, &phones)?;
+    let dnn = dnn::load(matches.value("dnn"), &phones)?;
+    let mut recognizer = Recognizer::new(&words, dnn);
+    if let Some(network) = command::network(matches, &words, lm.as_ref())? {
+        recognizer.set_network(network);
+    }
+    recognizer.set_params(params);
+    recognizer.set_keep_frame_stats(true);
+
+    // the network output does not depend on pruning, so only the search is timed
+    let utterances = command::compute_posteriors(matches, &mut recognizer)?;
+    let n_frames: usize = utterances.iter().map(|(_, posteriors)| posteriors.n_frames()).sum();
+
+    println!("{:>10} {:>10} {:>10} {:>8} {:>8} {:>10} {:>12} {:>12}",
+        "beam", "word-end", "max-active", "%WER", "%SER", "seconds", "frames/sec", "active/frame");
+    for &beam in beams.iter() {
+        for &word_end_beam in word_end_beams.iter() {
+            for &max_active in max_actives.iter() {
+                recognizer.set_pruning(Pruning { beam, word_end_beam, max_active: command::max_active(max_active) });
+
+                let start = Instant::now();
+                let mut n_expanded = 0f64;
+                let recognized: Vec<Transcription> = utterances.iter()
+                    .map(|(name, posteriors)| {
+                        let recognition = recognizer.decode_posteriors(posteriors);
+                        n_expanded += recognition.mean_expanded() * recognition.frame_stats.len() as f64;
+                        Transcription {
+                            name: name.clone(),
+                            words: recognition.transcript().into_iter().map(String::from).collect(),
+                        }
+                    })
+                    .collect();
+                let seconds = start.elapsed().as_secs_f64();
+
+                let summary = score::score(&reference, &recognized).summary();
+                println!("{:>10} {:>10} {:>10} {:>8.2} {:>8.2} {:>10.2} {:>12.0} {:>12.1}",
+                    beam, word_end_beam, max_active, summary.word_error_rate(), summary.sentence_error_rate(),
+                    seconds, n_frames as f64 / seconds, n_expanded / n_frames as f64);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use cli::{Command, Matches, Opt, UsageError};
+use command::{self, MODEL_OPTIONS, INPUT_OPTIONS, SCALE_OPTIONS, SEARCH_OPTIONS};
+use viterbi_rust::{fileutil, dnn, mlf, slf, Recognizer, Recognition};
+use viterbi_rust::constants::{N_DIMENSION, PRINT_PERCENT_COUNT};
+use viterbi_rust::json::NBestWriter;
+
+pub const OUTPUT_OPTIONS: &[Opt] = &[
+    Opt::value("output", "file", "recognized.txt", "recognized MLF to write"),
+    Opt::value("output-format", "words|times", "words", "MLF lines of words only, or 'start end word score' in 100ns units"),
+    Opt::optional("frame-stats", "file", "CSV of active states at every frame to write"),
+    Opt::value("n-best", "n", "1", "number of distinct word sequences to keep per utterance"),
+    Opt::optional("n-best-output", "file", "N-best list to write, as JSON if it ends with .json or else as an MLF"),
+    Opt::optional("lattice-dir", "dir", "directory to write an HTK SLF word lattice of each utterance to"),
+    Opt::optional("confidence-scale", "weight", "add word confidences from lattice posteriors with log probs scaled by this"),
+    Opt::value("chunk-frames", "n", "0", "feed a streaming session this many frames at a time, printing partial results; 0 for whole utterances"),
+];
+
+enum NBestOutput {
+    Mlf(File),
+    Json(NBestWriter<File>),
+}
+
+impl NBestOutput {
+    fn create(path: &str) -> command::Result<NBestOutput> {
+        let mut file = fileutil::create_file(path)?;
+        if path.ends_with(".json") {
+            Ok(NBestOutput::Json(NBestWriter::new(file)?))
+        } else {
+            mlf::write_header(&mut file)?;
+            Ok(NBestOutput::Mlf(file))
+        }
+    }
+
+    fn write(&mut self, name: &str, recognition: &Recognition) -> command::Result {
+        match self {
+            NBestOutput::Mlf(file) => {
+                let alternatives: Vec<Vec<&str>> = recognition.hypotheses.iter()
+                    .map(|hypothesis| hypothesis.transcript())
+                    .collect();
+                mlf::write_alternatives(file, name, &alternatives)?;
+            },
+            NBestOutput::Json(writer) => writer.write(name, &recognition.hypotheses)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> command::Result {
+        if let NBestOutput::Json(writer) = self {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
+
+pub const COMMAND: Command = Command {
+    name: "decode",
+    about: "recognize spectrograms and write the results as an MLF",
+    options: &[MODEL_OPTIONS, INPUT_OPTIONS, SCALE_OPTIONS, SEARCH_OPTIONS, OUTPUT_OPTIONS],
+};
+
+// decodes the spectrogram as if it arrived in chunks, printing the partial result after each
+fn decode_in_chunks<'m>(
+    recognizer: &mut Recognizer<'m>,
+    rec_name: &str,
+    spectrogram: &[[f64; N_DIMENSION]],
+    chunk_frames: usize,
+) -> command::Result<Recognition<'m>> {
+    let mut session = recognizer.session();
+    let mut n_frames = 0;
+    for chunk in spectrogram.chunks(chunk_frames) {
+        session.push_frames(chunk)?;
+        n_frames += chunk.len();
+        let partial = session.partial_result();
+        println!("{} {}: {} | {}", rec_name, n_frames, partial.stable.join(" "), partial.tentative.join(" "));
+    }
+    Ok(session.finalize()?)
+}
+
+// <dir>/tst/f/ak/1237743.lat for tst/f/ak/1237743.rec.
+// only the plain names of the rec name are kept, so that roots and .. never lead out of dir.
+fn lattice_path(dir: &str, rec_name: &str) -> PathBuf {
+    let relative: PathBuf = Path::new(rec_name).components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+    Path::new(dir).join(relative).with_extension("lat")
+}
+
+pub fn execute(matches: &Matches) -> command::Result {
+    let pruning = command::pruning(matches)?;
+    let params = command::params(matches)?;
+    let n_best: usize = matches.parse_value("n-best")?;
+    let confidence_scale = matches.parse_optional("confidence-scale")?;
+    if let Some(scale) = confidence_scale {
+        command::check_param("confidence-scale", "confidence-scale", scale)?;
+    }
+    let chunk_frames: usize = matches.parse_value("chunk-frames")?;
+    let with_times = match matches.value("output-format") {
+        "words" => false,
+        "times" => true,
+        format => return Err(Box::new(UsageError(format!("unknown output format '{}'", format)))),
+    };
+    let test_file_paths = command::input_paths(matches)?;
+    let cache = command::open_cache(matches)?;
+    let mut recognized_file = fileutil::create_file(matches.value("output"))?;
+
+    let phones = command::load_phones(matches)?;
+    let (words, lm) = command::load_words(matches, &phones)?;
+    let dnn = dnn::load(matches.value("dnn"), &phones)?;
+    let mut recognizer = Recognizer::new(&words, dnn);
+    if let Some(network) = command::network(matches, &words, lm.as_ref())? {
+        recognizer.set_network(network);
+    }
+    recognizer.set_pruning(pruning);
+    recognizer.set_params(params);
+    recognizer.set_n_best(n_best);
+    let lattice_dir = matches.optional("lattice-dir");
+    recognizer.set_keep_lattice(lattice_dir.is_some());
+    recognizer.set_confidence_scale(confidence_scale);
+    recognizer.set_keep_frame_stats(matches.optional("frame-stats").is_some());
+
+    let mut frame_stats_file = match matches.optional("frame-stats") {
+        Some(path) => {
+            let mut file = fileutil::create_file(path)?;
+            writeln!(file, "utterance,frame,active,expanded,best_log_prob")?;
+            Some(file)
+        },
+        None => None,
+    };
+    let mut n_best_output = match matches.optional("n-best-output") {
+        Some(path) => Some(NBestOutput::create(path)?),
+        None => None,
+    };
+
+    mlf::write_header(&mut recognized_file)?;
+
+    for (count, test_file_path) in test_file_paths.iter().enumerate() {
+        if count % PRINT_PERCENT_COUNT == 0 {
+            println!("{:.2}%..", count as f64 / test_file_paths.len() as f64 * 100f64);
+        }
+
+        let rec_name = command::get_rec_name(test_file_path)?;
+        let recognition = if chunk_frames > 0 {
+            match fileutil::read_spectrogram(test_file_path) {
+                Ok(spectrogram) => decode_in_chunks(&mut recognizer, &rec_name, &spectrogram, chunk_frames)?,
+                Err(error) => {
+                    eprintln!("skipping {}", error);
+                    continue;
+                },
+            }
+        } else {
+            let posteriors = command::read_posteriors(test_file_path, cache.as_ref(), |spectrogram| recognizer.compute_posteriors(spectrogram))?;
+            match posteriors {
+                Some(posteriors) => recognizer.decode_posteriors(&posteriors),
+                None => continue,
+            }
+        };
+        match recognition.best() {
+            Some(best) if with_times => mlf::write_labels(&mut recognized_file, &rec_name, &best.labels())?,
+            Some(best) if best.confidences.is_some() => {
+                let words: Vec<(&str, f64)> = best.labels().iter()
+                    .map(|label| (label.name, label.confidence.expect("Confidence")))
+                    .collect();
+                mlf::write_confidences(&mut recognized_file, &rec_name, &words)?;
+            },
+            Some(_) => mlf::write_transcription(&mut recognized_file, &rec_name, &recognition.transcript())?,
+            None => {
+                println!("No hypothesis for {}: pruning left no path the word network lets end", rec_name);
+                mlf::write_transcription(&mut recognized_file, &rec_name, &[])?;
+            },
+        }
+        if let Some(output) = n_best_output.as_mut() {
+            output.write(&rec_name, &recognition)?;
+        }
+        if let (Some(dir), Some(lattice)) = (lattice_dir, recognition.lattice.as_ref()) {
+            let path = lattice_path(dir, &rec_name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = fileutil::create_file(&path.to_string_lossy())?;
+            slf::write(&mut file, &rec_name, lattice, &words)?;
+        }
+
+        if let Some(file) = frame_stats_file.as_mut() {
+            for (t, stats) in recognition.frame_stats.iter().enumerate() {
+                writeln!(file, "{},{},{},{},{}", rec_name, t, stats.n_active, stats.n_expanded, stats.best_log_prob)?;
+            }
+        }
+    }
+
+    if let Some(output) = n_best_output {
+        output.finish()?;
+    }
+
+    println!("100%");
+    Ok(())
+}
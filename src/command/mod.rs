@@ -0,0 +1,204 @@
+pub mod decode;
+pub mod score;
+pub mod run;
+pub mod beam_report;
+pub mod priors;
+pub mod tune;
+pub mod align;
+
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::io;
+
+use cli::{Command, Matches, Opt, UsageError};
+use viterbi_rust::{error, fileutil, lm, network, params};
+use viterbi_rust::lm::LanguageModel;
+use viterbi_rust::network::Network;
+use viterbi_rust::params::Params;
+use viterbi_rust::dnn::Posteriors;
+use viterbi_rust::Recognizer;
+use viterbi_rust::cache::PosteriorCache;
+use viterbi_rust::constants::N_DIMENSION;
+use viterbi_rust::phone::{self, Phone};
+use viterbi_rust::word::{self, Word};
+use viterbi_rust::viterbi::Pruning;
+
+pub type Result<T = ()> = ::std::result::Result<T, Box<dyn Error>>;
+
+pub const COMMANDS: &[&Command] = &[&run::COMMAND, &decode::COMMAND, &score::COMMAND, &beam_report::COMMAND, &priors::COMMAND, &tune::COMMAND, &align::COMMAND];
+
+pub const MODEL_OPTIONS: &[Opt] = &[
+    Opt::value("hmm", "file", "hmm.txt", "HMM definitions of the phones"),
+    Opt::value("dictionary", "file", "dictionary.txt", "pronunciation dictionary"),
+    Opt::value("unigram", "file", "unigram.txt", "word head probabilities"),
+    Opt::value("bigram", "file", "bigram.txt", "word transition probabilities"),
+    Opt::optional("arpa", "file", "ARPA language model with backoff to use instead of --unigram and --bigram"),
+    Opt::optional("grammar", "file", "HTK SLF word network whose paths are the only ones decoded, instead of the language model"),
+    Opt::optional("min-words", "n", "only end utterances with at least this many words, not counting silences"),
+    Opt::optional("max-words", "n", "only end utterances with at most this many words, not counting silences"),
+    Opt::value("dnn", "dir", "dnn", "directory of the TensorFlow saved model"),
+];
+
+pub const INPUT_OPTIONS: &[Opt] = &[
+    Opt::value("input", "path", "tst", "spectrogram file or directory searched recursively"),
+    Opt::optional("input-list", "file", "file listing spectrogram paths, one per line, instead of --input"),
+    Opt::optional("cache-dir", "dir", "directory caching dnn outputs per utterance and model"),
+];
+
+// each overrides the params file, which overrides the defaults in brackets
+pub const SCALE_OPTIONS: &[Opt] = &[
+    Opt::optional("params", "file", "decoding parameters, one 'name value' line each for the options below"),
+    Opt::optional("acoustic-scale", "weight", "weight of the acoustic log likelihood [1]"),
+    Opt::optional("lm-scale", "weight", "weight of the language model log probability [1]"),
+    Opt::optional("word-penalty", "log-prob", "penalty subtracted at every word transition [110]"),
+    Opt::optional("prior-scale", "exponent", "divide posteriors by the label priors in <dnn>/priors.txt raised to this [1]"),
+];
+
+pub const SEARCH_OPTIONS: &[Opt] = &[
+    Opt::value("beam", "log-prob", "inf", "prune states scoring below the best of the frame by more than this"),
+    Opt::value("word-end-beam", "log-prob", "inf", "only states within this of the best of the frame may leave their word"),
+    Opt::value("max-active", "n", "0", "expand at most this many best states per frame, 0 for no limit"),
+];
+
+pub fn pruning(matches: &Matches) -> ::std::result::Result<Pruning, UsageError> {
+    Ok(Pruning {
+        beam: matches.parse_value("beam")?,
+        word_end_beam: matches.parse_value("word-end-beam")?,
+        max_active: max_active(matches.parse_value("max-active")?),
+    })
+}
+
+// a usage error for a value of the named parameter, given with the option, which no search can use
+pub fn check_param(option: &str, name: &str, value: f64) -> ::std::result::Result<(), UsageError> {
+    match params::expected(name, value) {
+        Some(expected) => Err(UsageError(format!("invalid value '{}' for '--{}', expected a {}", value, option, expected))),
+        None => Ok(()),
+    }
+}
+
+pub fn params(matches: &Matches) -> Result<Params> {
+    let mut params = match matches.optional("params") {
+        Some(path) => params::read(path)?,
+        None => Params::default(),
+    };
+    for name in params::NAMES.iter() {
+        if let Some(value) = matches.parse_optional(name)? {
+            check_param(name, name, value)?;
+            params.set(name, value);
+        }
+    }
+
+    Ok(params)
+}
+
+pub fn max_active(n: usize) -> usize {
+    if n == 0 { usize::MAX } else { n }
+}
+
+pub fn load_phones(matches: &Matches) -> viterbi_rust::Result<Vec<Phone>> {
+    phone::read_all(matches.value("hmm"))
+}
+
+// dictionary words with their bigram probabilities, and the ARPA model if one was given
+pub fn load_words<'p>(matches: &Matches, phones: &'p [Phone]) -> viterbi_rust::Result<(Vec<Word<'p>>, Option<LanguageModel>)> {
+    match matches.optional("arpa") {
+        Some(arpa_path) => {
+            let mut words = word::read_dictionary(matches.value("dictionary"), phones)?;
+            let lm = lm::read_all(arpa_path)?;
+            lm.set_bigram_probs(&mut words);
+            Ok((words, Some(lm)))
+        },
+        None => {
+            let words = word::read_all(
+                matches.value("dictionary"),
+                matches.value("unigram"),
+                matches.value("bigram"),
+                phones,
+            )?;
+            Ok((words, None))
+        },
+    }
+}
+
+// the grammar if one was given, or else the trigram network of an ARPA model of order 3,
+// either of which replaces the bigram loop of the words, then limited to the word counts asked for
+pub fn network(matches: &Matches, words: &[Word], lm: Option<&LanguageModel>) -> Result<Option<Network>> {
+    let network = match (matches.optional("grammar"), lm) {
+        (Some(grammar_path), _) => Some(network::read_slf(grammar_path, words)?),
+        (None, Some(lm)) if lm.order() >= 3 => Some(network::trigram(words, lm)),
+        _ => None,
+    };
+
+    let min_words: Option<usize> = matches.parse_optional("min-words")?;
+    let max_words: Option<usize> = matches.parse_optional("max-words")?;
+    if min_words.is_none() && max_words.is_none() {
+        return Ok(network);
+    }
+    let min_words = min_words.unwrap_or(0);
+    if let Some(max_words) = max_words.filter(|&max_words| max_words < min_words) {
+        return Err(Box::new(UsageError(format!("--min-words {} is above --max-words {}", min_words, max_words))));
+    }
+    let network = network.unwrap_or_else(|| network::bigram(words));
+    Ok(Some(network::count_words(&network, words, min_words, max_words)))
+}
+
+pub fn input_paths(matches: &Matches) -> viterbi_rust::Result<Vec<OsString>> {
+    match matches.optional("input-list") {
+        Some(list_path) => fileutil::read_file_list(list_path),
+        None => fileutil::list_test_file_paths(matches.value("input")),
+    }
+}
+
+pub fn open_cache(matches: &Matches) -> viterbi_rust::Result<Option<PosteriorCache>> {
+    match matches.optional("cache-dir") {
+        Some(dir) => PosteriorCache::open(dir, matches.value("dnn")).map(Some),
+        None => Ok(None),
+    }
+}
+
+// posteriors of a spectrogram file, computed and cached unless the cache has them.
+// None when the file can't be read, after reporting it.
+pub fn read_posteriors<F>(path: &OsStr, cache: Option<&PosteriorCache>, compute: F) -> viterbi_rust::Result<Option<Posteriors>>
+    where F: FnOnce(&[[f64; N_DIMENSION]]) -> viterbi_rust::Result<Posteriors>
+{
+    if let Some(posteriors) = cache.and_then(|cache| cache.load(path)) {
+        return Ok(Some(posteriors));
+    }
+
+    let spectrogram = match fileutil::read_spectrogram(path) {
+        Ok(spectrogram) => spectrogram,
+        Err(error) => {
+            eprintln!("skipping {}", error);
+            return Ok(None);
+        },
+    };
+    let posteriors = compute(&spectrogram)?;
+    if let Some(cache) = cache {
+        cache.store(path, &posteriors)?;
+    }
+
+    Ok(Some(posteriors))
+}
+
+// posteriors of every readable input keyed by the rec name, for decoding repeatedly
+pub fn compute_posteriors(matches: &Matches, recognizer: &mut Recognizer) -> Result<Vec<(String, Posteriors)>> {
+    let paths = input_paths(matches)?;
+    let cache = open_cache(matches)?;
+
+    let mut utterances = Vec::with_capacity(paths.len());
+    for path in paths.iter() {
+        if let Some(posteriors) = read_posteriors(path, cache.as_ref(), |spectrogram| recognizer.compute_posteriors(spectrogram))? {
+            utterances.push((get_rec_name(path)?, posteriors));
+        }
+    }
+
+    Ok(utterances)
+}
+
+// rec names go into MLF files, so the path must be UTF-8
+pub fn get_rec_name(test_file_path: &OsStr) -> error::Result<String> {
+    let path = test_file_path.to_str().ok_or_else(|| {
+        error::Error::io(&test_file_path.to_string_lossy(), io::Error::new(io::ErrorKind::InvalidData, "path is not valid UTF-8"))
+    })?;
+    Ok(str::replace(path, ".txt", ".rec"))
+}
@@ -0,0 +1,46 @@
+use cli::{Command, Matches, Opt};
+use command::{self, MODEL_OPTIONS, INPUT_OPTIONS};
+use viterbi_rust::dnn;
+use viterbi_rust::constants::PRINT_PERCENT_COUNT;
+use viterbi_rust::dnn::PriorCounts;
+
+pub const COMMAND: Command = Command {
+    name: "priors",
+    about: "estimate label priors as the mean dnn output over the input",
+    options: &[
+        MODEL_OPTIONS,
+        INPUT_OPTIONS,
+        &[
+            Opt::optional("output", "file", "priors file to write, <dnn>/priors.txt by default"),
+        ],
+    ],
+};
+
+pub fn execute(matches: &Matches) -> command::Result {
+    let test_file_paths = command::input_paths(matches)?;
+    let cache = command::open_cache(matches)?;
+    let output = match matches.optional("output") {
+        Some(path) => path.to_string(),
+        None => format!("{}/priors.txt", matches.value("dnn")),
+    };
+
+    let phones = command::load_phones(matches)?;
+    let mut dnn = dnn::load(matches.value("dnn"), &phones)?;
+
+    let mut counts = PriorCounts::default();
+    for (count, test_file_path) in test_file_paths.iter().enumerate() {
+        if count % PRINT_PERCENT_COUNT == 0 {
+            println!("{:.2}%..", count as f64 / test_file_paths.len() as f64 * 100f64);
+        }
+
+        let posteriors = command::read_posteriors(test_file_path, cache.as_ref(), |spectrogram| dnn.compute_posteriors(spectrogram))?;
+        if let Some(posteriors) = posteriors {
+            counts.add(&posteriors);
+        }
+    }
+
+    println!("100%");
+    dnn::write_priors(&output, &counts.priors())?;
+    println!("wrote priors of {} frames to {}", counts.n_frames(), output);
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use cli::{Command, Matches};
+use command::{self, decode, score, MODEL_OPTIONS, INPUT_OPTIONS, SCALE_OPTIONS, SEARCH_OPTIONS};
+
+pub const COMMAND: Command = Command {
+    name: "run",
+    about: "decode, then score the output (default when no command is given)",
+    options: &[MODEL_OPTIONS, INPUT_OPTIONS, SCALE_OPTIONS, SEARCH_OPTIONS, decode::OUTPUT_OPTIONS, score::SCORE_OPTIONS],
+};
+
+pub fn execute(matches: &Matches) -> command::Result {
+    decode::execute(matches)?;
+    score::print_scores(matches, matches.value("output"))
+}
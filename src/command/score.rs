@@ -0,0 +1,50 @@
+use std::io::Write;
+
+use cli::{Command, Matches, Opt};
+use command;
+use viterbi_rust::{fileutil, mlf, score};
+
+pub const SCORE_OPTIONS: &[Opt] = &[
+    Opt::value("reference", "file", "reference.txt", "reference MLF"),
+    Opt::value("vocabulary", "file", "vocabulary.txt", "words of the confusion matrix, one per line"),
+    Opt::value("worst-speakers", "n", "10", "number of worst speakers to list"),
+    Opt::value("confusion-csv", "file", "confusion.csv", "confusion matrix CSV to write"),
+    Opt::value("word-stats-csv", "file", "word_stats.csv", "per-word error statistics CSV to write"),
+];
+
+pub const COMMAND: Command = Command {
+    name: "score",
+    about: "score a recognized MLF against the reference MLF",
+    options: &[
+        &[Opt::value("recognized", "file", "recognized.txt", "recognized MLF to score")],
+        SCORE_OPTIONS,
+    ],
+};
+
+pub fn execute(matches: &Matches) -> command::Result {
+    print_scores(matches, matches.value("recognized"))
+}
+
+pub fn print_scores(matches: &Matches, recognized_path: &str) -> command::Result {
+    let n_worst_speakers: usize = matches.parse_value("worst-speakers")?;
+
+    let reference = mlf::read(matches.value("reference"))?;
+    let recognized = mlf::read(recognized_path)?;
+    let scores = score::score(&reference, &recognized);
+
+    for key in scores.missing.iter() {
+        println!("No reference for {}", key);
+    }
+    println!("{}", scores.summary());
+    print!("{}", score::Breakdown::of(&scores).display_worst(n_worst_speakers));
+
+    let vocabulary = score::read_vocabulary(matches.value("vocabulary"))?;
+    let confusion = score::Confusion::of(&vocabulary, &scores);
+    print!("{}", confusion);
+
+    let mut matrix_file = fileutil::create_file(matches.value("confusion-csv"))?;
+    matrix_file.write_all(confusion.matrix_csv().as_bytes())?;
+    let mut word_stats_file = fileutil::create_file(matches.value("word-stats-csv"))?;
+    word_stats_file.write_all(confusion.word_stats_csv().as_bytes())?;
+    Ok(())
+}
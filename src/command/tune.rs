@@ -0,0 +1,164 @@
+use cli::{Command, Matches, Opt, UsageError};
+use command::{self, MODEL_OPTIONS, INPUT_OPTIONS, SCALE_OPTIONS, SEARCH_OPTIONS};
+use viterbi_rust::{dnn, mlf, params, score, Params, Recognizer};
+use viterbi_rust::dnn::Posteriors;
+use viterbi_rust::mlf::Transcription;
+use viterbi_rust::score::Summary;
+
+pub const COMMAND: Command = Command {
+    name: "tune",
+    about: "search decoding parameters for the lowest word error rate, running the dnn only once",
+    options: &[
+        MODEL_OPTIONS,
+        INPUT_OPTIONS,
+        SCALE_OPTIONS,
+        SEARCH_OPTIONS,
+        &[
+            Opt::value("reference", "file", "reference.txt", "reference MLF"),
+            Opt::optional("acoustic-scales", "list", "comma separated acoustic scales to try"),
+            Opt::optional("lm-scales", "list", "comma separated language model scales to try"),
+            Opt::optional("word-penalties", "list", "comma separated word penalties to try"),
+            Opt::optional("prior-scales", "list", "comma separated prior scales to try"),
+            Opt::value("method", "grid|coordinate", "grid",
+                "try every combination, or the best of one parameter at a time until nothing changes"),
+            Opt::value("output", "file", "tuned_params.txt", "parameters file to write the best setting to"),
+        ],
+    ],
+};
+
+// option of the values to try for each parameter
+const AXES: &[(&str, &str)] = &[
+    ("acoustic-scales", "acoustic-scale"),
+    ("lm-scales", "lm-scale"),
+    ("word-penalties", "word-penalty"),
+    ("prior-scales", "prior-scale"),
+];
+
+const MAX_ROUNDS: usize = 10; // of the coordinate search
+
+pub fn execute(matches: &Matches) -> command::Result {
+    let start = command::params(matches)?;
+    let pruning = command::pruning(matches)?;
+    let mut axes = Vec::new();
+    for &(option, name) in AXES.iter() {
+        if matches.optional(option).is_some() {
+            let values = matches.parse_list::<f64>(option)?;
+            for &value in values.iter() {
+                command::check_param(option, name, value)?;
+            }
+            axes.push((name, values));
+        }
+    }
+    let method = matches.value("method");
+    if method != "grid" && method != "coordinate" {
+        return Err(Box::new(UsageError(format!("unknown method '{}'", method))));
+    }
+
+    let reference = mlf::read(matches.value("reference"))?;
+
+    let phones = command::load_phones(matches)?;
+    let (words, lm) = command::load_words(matches, &phones)?;
+    let dnn = dnn::load(matches.value("dnn"), &phones)?;
+    let mut recognizer = Recognizer::new(&words, dnn);
+    if let Some(network) = command::network(matches, &words, lm.as_ref())? {
+        recognizer.set_network(network);
+    }
+    recognizer.set_pruning(pruning);
+
+    let utterances = command::compute_posteriors(matches, &mut recognizer)?;
+
+    println!("{:>14} {:>10} {:>12} {:>11} {:>8} {:>8}",
+        "acoustic-scale", "lm-scale", "word-penalty", "prior-scale", "%WER", "%SER");
+    let mut tuner = Tuner { recognizer, utterances: &utterances, reference: &reference, results: Vec::new() };
+    let (best, summary) = match method {
+        "grid" => tuner.grid(start, &axes),
+        _ => tuner.coordinate(start, &axes),
+    };
+
+    println!("best after {} settings: {}", tuner.results.len(), summary);
+    print!("{}", best);
+    params::write(matches.value("output"), &best)?;
+    Ok(())
+}
+
+struct Tuner<'a, 'm> {
+    recognizer: Recognizer<'m>,
+    utterances: &'a [(String, Posteriors)],
+    reference: &'a [Transcription],
+    results: Vec<(Params, Summary)>, // settings decoded so far
+}
+
+impl<'a, 'm> Tuner<'a, 'm> {
+    fn grid(&mut self, start: Params, axes: &[(&str, Vec<f64>)]) -> (Params, Summary) {
+        let mut settings = vec![start];
+        for &(name, ref values) in axes.iter() {
+            settings = settings.iter()
+                .flat_map(|params| values.iter().map(move |&value| {
+                    let mut params = *params;
+                    params.set(name, value);
+                    params
+                }))
+                .collect();
+        }
+
+        let mut best = (start, self.evaluate(start));
+        for &params in settings.iter() {
+            let summary = self.evaluate(params);
+            if is_better(&summary, &best.1) {
+                best = (params, summary);
+            }
+        }
+        best
+    }
+
+    // moves one parameter at a time to its best value with the others fixed
+    fn coordinate(&mut self, start: Params, axes: &[(&str, Vec<f64>)]) -> (Params, Summary) {
+        let mut best = (start, self.evaluate(start));
+        for _ in 0..MAX_ROUNDS {
+            let round_start = best.0;
+            for &(name, ref values) in axes.iter() {
+                let center = best.0;
+                for &value in values.iter() {
+                    let mut params = center;
+                    params.set(name, value);
+                    let summary = self.evaluate(params);
+                    if is_better(&summary, &best.1) {
+                        best = (params, summary);
+                    }
+                }
+            }
+
+            if best.0 == round_start {
+                break;
+            }
+        }
+        best
+    }
+
+    // decodes the cached posteriors with params unless done before
+    fn evaluate(&mut self, params: Params) -> Summary {
+        if let Some(&(_, summary)) = self.results.iter().find(|(done, _)| *done == params) {
+            return summary;
+        }
+
+        self.recognizer.set_params(params);
+        let recognized: Vec<Transcription> = self.utterances.iter()
+            .map(|(name, posteriors)| Transcription {
+                name: name.clone(),
+                words: self.recognizer.decode_posteriors(posteriors).transcript().into_iter().map(String::from).collect(),
+            })
+            .collect();
+        let summary = score::score(self.reference, &recognized).summary();
+
+        println!("{:>14} {:>10} {:>12} {:>11} {:>8.2} {:>8.2}",
+            params.acoustic_scale, params.lm_scale, params.word_penalty, params.prior_scale,
+            summary.word_error_rate(), summary.sentence_error_rate());
+        self.results.push((params, summary));
+        summary
+    }
+}
+
+// lower word error rate, then lower sentence error rate
+fn is_better(summary: &Summary, best: &Summary) -> bool {
+    (summary.word_error_rate(), summary.sentence_error_rate()) < (best.word_error_rate(), best.sentence_error_rate())
+}
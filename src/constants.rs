@@ -1,3 +1,8 @@
 pub const N_DIMENSION: usize = 39;
+pub const FRAME_PERIOD: u64 = 100000; // in HTK time units of 100ns
 pub const WORD_PENALTY: f64 = 110f64;
+pub const SENTENCE_BOUNDARY: &str = "<s>";
+pub const SILENCE_PHONES: &[&str] = &["sil", "sp"];
 pub const PRINT_PERCENT_COUNT: usize = 60;
+pub const TRACEBACK_INTERVAL: usize = 100; // frames between partial tracebacks
+pub const MAX_BATCH_FRAMES: usize = 1000; // spectrum windows fed to the network per run
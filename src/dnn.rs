@@ -1,47 +1,143 @@
 extern crate tensorflow;
 
-use std::num::ParseIntError;
+use std::ffi::OsStr;
+use std::ops::Range;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
 use self::tensorflow::{self as tf};
+use constants::*;
+use error::{Error, Result};
 use phone::{self, Phone};
+use params::Params;
 use fileutil;
 
 pub struct Dnn {
-    graph: tf::Graph,
     session: tf::Session,
-    label_info: Vec<(usize, usize)>,
+    input_op: tf::Operation,
+    output_op: tf::Operation,
+    label_info: Vec<(usize, usize)>, // (phone index, state) of each label
+    state_labels: Vec<Vec<Option<usize>>>, // label of each [phone index][state]
+    log_priors: Option<Vec<f64>>, // natural log prior of each label
     pub spectrum_window_range: (i32, i32),
 }
 
-impl Dnn {
-    pub fn compute_observation_prob(&mut self, spectrum_window: &[f32], phones: &[Phone]) -> Vec<Vec<f32>> {
-        let mut observation_prob = Vec::new();
-        for phone in phones.iter() {
-            observation_prob.push(vec![0f32; phone.n_states]);
+// where the search finds the acoustic score of each phone state in the dnn outputs
+#[derive(Clone, Debug)]
+pub struct LabelMap {
+    pub state_labels: Vec<Vec<Option<usize>>>, // label of each [phone index][state]
+    pub log_offsets: Vec<f64>, // added to the log posterior of each label
+    pub acoustic_scale: f64, // multiplies the offset log posterior
+}
+
+// accumulates dnn outputs to estimate label priors
+#[derive(Clone, Debug, Default)]
+pub struct PriorCounts {
+    counts: Vec<f64>,
+    n_frames: usize,
+}
+
+// dnn outputs of every frame of an utterance
+#[derive(Clone, Debug)]
+pub struct Posteriors {
+    pub n_labels: usize,
+    pub values: Vec<f32>, // row major [n_frames, n_labels]
+}
+
+impl Posteriors {
+    pub fn n_frames(&self) -> usize {
+        self.values.len() / self.n_labels.max(1)
+    }
+
+    pub fn frame(&self, t: usize) -> &[f32] {
+        &self.values[t * self.n_labels..(t + 1) * self.n_labels]
+    }
+}
+
+impl PriorCounts {
+    pub fn add(&mut self, posteriors: &Posteriors) {
+        self.counts.resize(posteriors.n_labels, 0f64);
+        for t in 0..posteriors.n_frames() {
+            for (count, &prob) in self.counts.iter_mut().zip(posteriors.frame(t)) {
+                *count += prob as f64;
+            }
         }
+        self.n_frames += posteriors.n_frames();
+    }
 
-        let output = self.predict(spectrum_window).expect("can't predict dnn output");
+    pub fn n_frames(&self) -> usize {
+        self.n_frames
+    }
 
-        for (index, prob) in output.into_iter().enumerate() {
-            let (phone_index, state_num) = self.label_info[index];
-            observation_prob[phone_index][state_num] = prob;
+    // mean posterior of each label
+    pub fn priors(&self) -> Vec<f64> {
+        self.counts.iter().map(|&count| count / self.n_frames.max(1) as f64).collect()
+    }
+}
+
+impl Dnn {
+    // posteriors are divided by priors raised to the prior scale, giving scaled likelihoods.
+    // without priors.txt the posteriors are used as they are.
+    pub fn label_map(&self, params: &Params) -> LabelMap {
+        let log_offsets = match self.log_priors {
+            Some(ref log_priors) => log_priors.iter().map(|&log_prior| -params.prior_scale * log_prior).collect(),
+            None => vec![0f64; self.label_info.len()],
+        };
+
+        LabelMap { state_labels: self.state_labels.clone(), log_offsets, acoustic_scale: params.acoustic_scale }
+    }
+
+    // frames after a frame which its spectrum window takes
+    pub fn right_context(&self) -> usize {
+        (self.spectrum_window_range.1 - 1).max(0) as usize
+    }
+
+    // runs the network on the spectrum windows of all frames
+    pub fn compute_posteriors(&mut self, spectrogram: &[[f64; N_DIMENSION]]) -> Result<Posteriors> {
+        self.compute_frame_posteriors(spectrogram, 0, 0..spectrogram.len())
+    }
+
+    // runs the network on the spectrum windows of the frames in the range, counted from
+    // the start of the utterance while `spectrogram` holds its frames from `offset` on.
+    // windows are fed MAX_BATCH_FRAMES at a time, so the input tensor stays bounded on long recordings.
+    // windows reaching past the end of `spectrogram` repeat its last frame, as at the end of an utterance,
+    // and windows must not reach back before `offset` unless it is 0.
+    pub fn compute_frame_posteriors(
+        &mut self,
+        spectrogram: &[[f64; N_DIMENSION]],
+        offset: usize,
+        frames: Range<usize>,
+    ) -> Result<Posteriors> {
+        let n_frames = frames.len();
+        if n_frames == 0 {
+            return Ok(Posteriors { n_labels: self.label_info.len(), values: Vec::new() });
         }
 
-        observation_prob
+        let mut values = Vec::with_capacity(n_frames * self.label_info.len());
+        for batch_start in frames.clone().step_by(MAX_BATCH_FRAMES) {
+            let batch_end = (batch_start + MAX_BATCH_FRAMES).min(frames.end);
+            let mut windows = Vec::new();
+            for t in batch_start..batch_end {
+                windows.extend(make_spectrum_window(spectrogram, offset, t, self.spectrum_window_range));
+            }
+            values.extend(self.predict(&windows, batch_end - batch_start)?);
+        }
+
+        Ok(Posteriors { n_labels: self.label_info.len(), values })
     }
 
-    fn predict(&mut self, input: &[f32]) -> tf::Result<Vec<f32>> {
-        let inputs: tf::Tensor<f32> = tf::Tensor::new(&[1u64, input.len() as u64]);
+    // input holds n_frames spectrum windows one after another
+    fn predict(&mut self, input: &[f32], n_frames: usize) -> tf::Result<Vec<f32>> {
+        let window_len = input.len() / n_frames.max(1);
+        let inputs: tf::Tensor<f32> = tf::Tensor::new(&[n_frames as u64, window_len as u64]);
         let inputs = inputs.with_values(input)?;
 
         let mut args = tf::SessionRunArgs::new();
 
-        let input_op = self.graph.operation_by_name_required("inputs")?;
-        let output_op = self.graph.operation_by_name_required("outputs/Softmax")?;
-
-        args.add_feed(&input_op,0, &inputs);
-        args.add_target(&output_op);
+        args.add_feed(&self.input_op, 0, &inputs);
+        args.add_target(&self.output_op);
 
-        let output_token = args.request_fetch(&output_op, 0);
+        let output_token = args.request_fetch(&self.output_op, 0);
 
         self.session.run(&mut args)?;
 
@@ -51,8 +147,32 @@ impl Dnn {
     }
 }
 
-pub fn load(dir: &str, phones: &[Phone]) -> tf::Result<Dnn> {
-    println!("tensorflow version: {:?}", tensorflow::version()?);
+// spectrogram holds the frames from offset on
+fn make_spectrum_window(spectrogram: &[[f64; N_DIMENSION]], offset: usize, index: usize, range: (i32, i32)) -> Vec<f32> {
+    let (start, end) = range;
+    let mut spectrum_window = Vec::new();
+    for delta in start..end {
+        let t = index as i32 + delta - offset as i32;
+        let spectrum =
+            if t < 0 {
+                &spectrogram[0]
+            }
+            else if t >= spectrogram.len() as i32 {
+                &spectrogram[spectrogram.len() - 1]
+            } else {
+                &spectrogram[t as usize]
+            };
+
+        for value in spectrum.iter() {
+            spectrum_window.push(*value as f32);
+        }
+    }
+
+    spectrum_window
+}
+
+pub fn load(dir: &str, phones: &[Phone]) -> Result<Dnn> {
+    println!("tensorflow version: {:?}", tensorflow::version().map_err(tf::Status::from)?);
 
     let mut graph = tf::Graph::new();
 
@@ -64,35 +184,107 @@ pub fn load(dir: &str, phones: &[Phone]) -> tf::Result<Dnn> {
     )?;
 
     let label_info_path = format!("{}/label_info.txt", dir);
-    let label_info = load_label_info(&label_info_path, phones).unwrap();
+    let label_info = load_label_info(&label_info_path, phones)?;
 
     let range_path = format!("{}/spectrum_window_range.txt", dir);
-    let spectrum_window_range = load_spectrum_window_range(&range_path).unwrap();
+    let spectrum_window_range = load_spectrum_window_range(&range_path)?;
+
+    let priors_path = format!("{}/priors.txt", dir);
+    let log_priors = if Path::new(&priors_path).exists() {
+        Some(load_priors(&priors_path, label_info.len())?)
+    } else {
+        None
+    };
+
+    let input_op = graph.operation_by_name_required("inputs")?;
+    let output_op = graph.operation_by_name_required("outputs/Softmax")?;
+    let mut state_labels: Vec<Vec<Option<usize>>> = phones.iter()
+        .map(|phone| vec![None; phone.n_states])
+        .collect();
+    for (label, &(phone_index, state)) in label_info.iter().enumerate() {
+        state_labels[phone_index][state] = Some(label);
+    }
+
+    Ok(Dnn { session, input_op, output_op, label_info, state_labels, log_priors, spectrum_window_range })
+}
 
-    Ok(Dnn { graph, session, label_info, spectrum_window_range })
+fn parse_element<T: FromStr>(path: &str, line: usize, elements: &[&str], i: usize, expected: &str) -> Result<T> {
+    let element = elements.get(i).cloned().unwrap_or("");
+    element.parse().map_err(|_| Error::parse(path, line, element, expected))
 }
 
-fn load_label_info(path: &str, phones: &[Phone]) -> Result<Vec<(usize, usize)>, ParseIntError> {
-    let lines = fileutil::read_lines(std::ffi::OsStr::new(path));
+fn load_label_info(path: &str, phones: &[Phone]) -> Result<Vec<(usize, usize)>> {
+    let lines = fileutil::read_lines(OsStr::new(path))?;
 
     let mut label_info = vec![(0 as usize, 0 as usize); lines.len()];
-    for line in lines.iter() {
+    for (number, line) in lines.iter().enumerate() {
         let elements: Vec<_> = line.split_whitespace().collect();
-        let label_index: usize = elements[0].parse()?;
-        let phone_name = elements[1];
-        let state_num: usize = elements[2].parse()?;
+        let label_index: usize = parse_element(path, number + 1, &elements, 0, "label index")?;
+        let phone_name = elements.get(1).cloned().unwrap_or("");
+        let state_num: usize = parse_element(path, number + 1, &elements, 2, "state number")?;
 
-        let phone_index = phone::find(phone_name, phones).index;
-        label_info[label_index] = (phone_index, state_num - 1);
+        let phone = phone::find(phone_name, phones).ok_or_else(|| Error::UnknownPhone {
+            path: path.to_string(),
+            line: number + 1,
+            name: phone_name.to_string(),
+        })?;
+        if label_index >= label_info.len() {
+            return Err(Error::parse(path, number + 1, elements[0], &format!("label index below {}", label_info.len())));
+        }
+        if state_num < 1 || state_num > phone.n_states {
+            return Err(Error::parse(path, number + 1, elements[2], &format!("state number of {} from 1 to {}", phone.name, phone.n_states)));
+        }
+        label_info[label_index] = (phone.index, state_num - 1);
     }
 
     Ok(label_info)
 }
 
-fn load_spectrum_window_range(path: &str) -> Result<(i32, i32), ParseIntError> {
-    let lines = fileutil::read_lines(std::ffi::OsStr::new(path));
+// reads "label_index prior" lines into natural log priors.
+// priors may also be frame counts, e.g. from an alignment; they are normalized to sum to one.
+fn load_priors(path: &str, n_labels: usize) -> Result<Vec<f64>> {
+    let lines = fileutil::read_lines(OsStr::new(path))?;
+
+    let mut priors = vec![None; n_labels];
+    for (number, line) in lines.iter().enumerate() {
+        let elements: Vec<_> = line.split_whitespace().collect();
+        if elements.is_empty() {
+            continue;
+        }
+
+        let label_index: usize = parse_element(path, number + 1, &elements, 0, "label index")?;
+        if label_index >= n_labels {
+            return Err(Error::parse(path, number + 1, elements[0], &format!("label index below {}", n_labels)));
+        }
+        let prior: f64 = parse_element(path, number + 1, &elements, 1, "prior")?;
+        if prior <= 0f64 || !prior.is_finite() {
+            return Err(Error::parse(path, number + 1, elements[1], "positive prior"));
+        }
+        priors[label_index] = Some(prior);
+    }
+
+    if let Some(label_index) = priors.iter().position(|prior| prior.is_none()) {
+        return Err(Error::parse(path, lines.len() + 1, "", &format!("prior of label {}", label_index)));
+    }
+
+    let priors: Vec<f64> = priors.into_iter().map(|prior| prior.unwrap()).collect();
+    let total: f64 = priors.iter().sum();
+    Ok(priors.iter().map(|prior| (prior / total).ln()).collect())
+}
+
+pub fn write_priors(path: &str, priors: &[f64]) -> Result<()> {
+    let mut file = fileutil::create_file(path)?;
+    for (label_index, prior) in priors.iter().enumerate() {
+        writeln!(file, "{} {:e}", label_index, prior).map_err(|error| Error::io(path, error))?;
+    }
+
+    Ok(())
+}
+
+fn load_spectrum_window_range(path: &str) -> Result<(i32, i32)> {
+    let lines = fileutil::read_lines(OsStr::new(path))?;
 
-    let elements: Vec<_> = lines[0].split_whitespace().collect();
+    let elements: Vec<_> = lines.first().map(|line| line.split_whitespace().collect()).unwrap_or_default();
 
-    Ok((elements[0].parse()?, elements[1].parse()?))
+    Ok((parse_element(path, 1, &elements, 0, "window start")?, parse_element(path, 1, &elements, 1, "window end")?))
 }
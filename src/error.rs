@@ -0,0 +1,62 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use tensorflow as tf;
+
+#[derive(Debug)]
+pub enum Error {
+    Io { path: String, error: io::Error },
+    // an unexpected token at a line (counted from 1) of a model or data file
+    Parse { path: String, line: usize, token: String, expected: String },
+    UnknownPhone { path: String, line: usize, name: String },
+    NoFrames { path: String },
+    Tensorflow(tf::Status),
+}
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io(path: &str, error: io::Error) -> Error {
+        Error::Io { path: path.to_string(), error }
+    }
+
+    pub fn parse(path: &str, line: usize, token: &str, expected: &str) -> Error {
+        Error::Parse {
+            path: path.to_string(),
+            line,
+            token: token.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, error } => write!(f, "{}: {}", path, error),
+            Error::Parse { path, line, token, expected } =>
+                write!(f, "{}:{}: expected {}, found {:?}", path, line, expected, token),
+            Error::UnknownPhone { path, line, name } =>
+                write!(f, "{}:{}: unknown phone {:?}", path, line, name),
+            Error::NoFrames { path } => write!(f, "{}: no spectrum frames", path),
+            Error::Tensorflow(status) => write!(f, "tensorflow: {}", status),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { error, .. } => Some(error),
+            Error::Tensorflow(status) => Some(status),
+            _ => None,
+        }
+    }
+}
+
+impl From<tf::Status> for Error {
+    fn from(status: tf::Status) -> Error {
+        Error::Tensorflow(status)
+    }
+}
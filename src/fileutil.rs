@@ -4,48 +4,65 @@ use std::io::{self, Read};
 use std::ffi::{OsString, OsStr};
 
 use constants::*;
+use error::{Error, Result};
 
-pub fn create_file(file_path: &str) -> File {
+pub fn create_file(file_path: &str) -> Result<File> {
     let path = Path::new(file_path);
-    File::create(path).expect("Can't create file")
+    File::create(path).map_err(|error| Error::io(file_path, error))
 }
 
-pub fn read_lines(file_path: &OsStr) -> Vec<String> {
+pub fn read_lines(file_path: &OsStr) -> Result<Vec<String>> {
     let path = Path::new(file_path);
-    let file = File::open(path).expect("Can't open file");
-    read_lines_from_file(file)
+    read_lines_from_path(path).map_err(|error| Error::io(&path.to_string_lossy(), error))
 }
 
-pub fn read_spectrogram(file_path: &OsStr) -> Vec<[f64; N_DIMENSION]> {
-    let lines = read_lines(file_path);
+pub fn read_spectrogram(file_path: &OsStr) -> Result<Vec<[f64; N_DIMENSION]>> {
+    let path = file_path.to_string_lossy();
+    let lines = read_lines(file_path)?;
     let mut spectrogram = Vec::new();
 
-    for line in lines[1..].iter() {
+    for (number, line) in lines.iter().enumerate().skip(1) {
         let values: Vec<&str> = line.split_whitespace().collect();
 
         if values.len() == N_DIMENSION {
             let mut spectrum = [0f64; N_DIMENSION];
             for i in 0..N_DIMENSION {
-                spectrum[i] = values[i].parse().expect("Spectrum Value");
+                spectrum[i] = values[i].parse()
+                    .map_err(|_| Error::parse(&path, number + 1, values[i], "spectrum value"))?;
             }
             spectrogram.push(spectrum);
         }
     }
 
-    spectrogram
+    if spectrogram.is_empty() {
+        return Err(Error::NoFrames { path: path.into_owned() });
+    }
+
+    Ok(spectrogram)
+}
+
+pub fn list_test_file_paths(directory_path: &str) -> Result<Vec<OsString>> {
+    list_files(Path::new(directory_path)).map_err(|error| Error::io(directory_path, error))
 }
 
-pub fn list_test_file_paths(directory_path: &str) -> Vec<OsString> {
-    list_files(Path::new(directory_path)).expect("Can't list test files")
+// reads paths listed one per line, ignoring blank lines
+pub fn read_file_list(list_path: &str) -> Result<Vec<OsString>> {
+    let paths = read_lines(OsStr::new(list_path))?.into_iter()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .map(OsString::from)
+        .collect();
+    Ok(paths)
 }
 
-fn read_lines_from_file(mut file: File) -> Vec<String> {
+fn read_lines_from_path(path: &Path) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
     let mut buffer = String::new();
-    file.read_to_string(&mut buffer).expect("Can't read");
+    file.read_to_string(&mut buffer)?;
 
-    buffer.lines()
+    Ok(buffer.lines()
         .map(|s: &str| String::from(s))
-        .collect()
+        .collect())
 }
 
 fn list_files(path: &Path) -> io::Result<Vec<OsString>> {
@@ -63,8 +80,10 @@ fn list_files(path: &Path) -> io::Result<Vec<OsString>> {
         }
 
         Ok(files)
-    } else {
+    } else if path.exists() {
         files.push(path.as_os_str().to_os_string());
         Ok(files)
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, "No such file or directory"))
     }
 }
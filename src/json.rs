@@ -0,0 +1,79 @@
+use std::io::{self, Write};
+
+use viterbi::Hypothesis;
+
+// writes N-best hypotheses as a JSON array with one object per utterance:
+// {"name": .., "hypotheses": [{"words": [..], "log_prob": .., "acoustic_log_prob": .., "lm_log_prob": ..}, ..]}
+// with "confidences": [..] after the words when the hypotheses have them
+pub struct NBestWriter<W: Write> {
+    writer: W,
+    n_utterances: usize,
+}
+
+impl<W: Write> NBestWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<NBestWriter<W>> {
+        write!(writer, "[")?;
+        Ok(NBestWriter { writer, n_utterances: 0 })
+    }
+
+    pub fn write(&mut self, name: &str, hypotheses: &[Hypothesis]) -> io::Result<()> {
+        let separator = if self.n_utterances == 0 { "" } else { "," };
+        write!(self.writer, "{}\n  {{\"name\": {}, \"hypotheses\": [", separator, string(name))?;
+
+        for (rank, hypothesis) in hypotheses.iter().enumerate() {
+            let words: Vec<String> = hypothesis.transcript().into_iter().map(string).collect();
+            let confidences = match hypothesis.confidences {
+                Some(_) => {
+                    let confidences: Vec<String> = hypothesis.labels().iter()
+                        .map(|label| number(label.confidence.expect("Confidence")))
+                        .collect();
+                    format!(", \"confidences\": [{}]", confidences.join(", "))
+                },
+                None => String::new(),
+            };
+            write!(self.writer, "{}\n    {{\"words\": [{}]{}, \"log_prob\": {}, \"acoustic_log_prob\": {}, \"lm_log_prob\": {}}}",
+                if rank == 0 { "" } else { "," },
+                words.join(", "),
+                confidences,
+                number(hypothesis.log_prob),
+                number(hypothesis.acoustic_log_prob),
+                number(hypothesis.lm_log_prob))?;
+        }
+
+        write!(self.writer, "\n  ]}}")?;
+        self.n_utterances += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        writeln!(self.writer, "\n]")?;
+        Ok(self.writer)
+    }
+}
+
+pub fn string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// null for infinities and NaN, which JSON lacks
+pub fn number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    } else {
+        "null".to_string()
+    }
+}
@@ -0,0 +1,31 @@
+//! Viterbi digit recognizer with a DNN-HMM acoustic model.
+//!
+//! Models are loaded with `phone::read_all` (HMM topologies), `word::read_all`
+//! (dictionary and bigram language model, or `lm::read_all` for an ARPA file) and `dnn::load` (TensorFlow saved model),
+//! then handed to a `Recognizer` which decodes spectrograms into a `Recognition`.
+//! Score weights and the word penalty are set at runtime through `Params`.
+//! `Recognizer::set_network` replaces the bigram word loop, e.g. with a grammar from `network::read_slf`
+//! or a network whose word count `network::count_words` limits.
+
+extern crate tensorflow;
+
+pub mod error;
+pub mod fileutil;
+pub mod constants;
+pub mod phone;
+pub mod word;
+pub mod lm;
+pub mod network;
+pub mod dnn;
+pub mod cache;
+pub mod params;
+pub mod viterbi;
+pub mod mlf;
+pub mod json;
+pub mod slf;
+pub mod score;
+mod recognizer;
+
+pub use error::{Error, Result};
+pub use params::Params;
+pub use recognizer::{Recognizer, Recognition, Session, PartialResult};
@@ -0,0 +1,122 @@
+pub mod read;
+
+pub use self::read::read_arpa as read_all;
+
+use std::collections::HashMap;
+
+use word::Word;
+
+pub const SENTENCE_START: &str = "<s>";
+pub const SENTENCE_END: &str = "</s>";
+pub const UNKNOWN_WORD: &str = "<unk>";
+
+// name of a dictionary word in the model when it comes before other words,
+// where the sentence boundary word starts a sentence
+pub fn history_name<'a>(word: &'a Word) -> &'a str {
+    if word.is_sentence_boundary() { SENTENCE_START } else { &word.name }
+}
+
+// name of a dictionary word in the model when it is predicted, where the sentence boundary word ends a sentence
+pub fn predicted_name<'a>(word: &'a Word) -> &'a str {
+    if word.is_sentence_boundary() { SENTENCE_END } else { &word.name }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NGram {
+    pub log_prob: f64, // log10, as in ARPA files
+    pub backoff: f64, // log10 weight of the n-gram as a history, 0 when absent
+}
+
+// backoff n-gram language model
+#[derive(Debug)]
+pub struct LanguageModel {
+    vocabulary: HashMap<String, usize>,
+    ngrams: Vec<HashMap<Vec<usize>, NGram>>, // ngrams[n - 1] by word ids
+}
+
+impl LanguageModel {
+    pub fn new(ngrams_by_order: Vec<Vec<(Vec<String>, NGram)>>) -> LanguageModel {
+        let mut vocabulary = HashMap::new();
+        let mut ngrams = Vec::with_capacity(ngrams_by_order.len());
+        for order_ngrams in ngrams_by_order.into_iter() {
+            let mut by_ids = HashMap::with_capacity(order_ngrams.len());
+            for (names, ngram) in order_ngrams.into_iter() {
+                let ids = names.into_iter()
+                    .map(|name| {
+                        let next_id = vocabulary.len();
+                        *vocabulary.entry(name).or_insert(next_id)
+                    })
+                    .collect();
+                by_ids.insert(ids, ngram);
+            }
+            ngrams.push(by_ids);
+        }
+
+        LanguageModel { vocabulary, ngrams }
+    }
+
+    pub fn order(&self) -> usize {
+        self.ngrams.len()
+    }
+
+    // id of a word, or of <unk> for words out of the vocabulary
+    fn id(&self, name: &str) -> Option<usize> {
+        self.vocabulary.get(name)
+            .or_else(|| self.vocabulary.get(UNKNOWN_WORD))
+            .cloned()
+    }
+
+    // log10 probability of a word after the history, oldest word first.
+    // unseen n-grams back off to shorter histories, adding the backoff weight of the history left out.
+    pub fn log10_prob(&self, history: &[&str], word: &str) -> f64 {
+        let word = match self.id(word) {
+            Some(id) => id,
+            None => return f64::NEG_INFINITY,
+        };
+        let history: Option<Vec<usize>> = history.iter().map(|name| self.id(name)).collect();
+        match history {
+            Some(history) => self.ids_log10_prob(&history, word),
+            // a history out of the vocabulary backs off to the word alone
+            None => self.ids_log10_prob(&[], word),
+        }
+    }
+
+    fn ids_log10_prob(&self, history: &[usize], word: usize) -> f64 {
+        let history = &history[history.len().saturating_sub(self.order() - 1)..];
+        let mut ngram = history.to_vec();
+        ngram.push(word);
+        if let Some(found) = self.ngrams[history.len()].get(&ngram) {
+            return found.log_prob;
+        }
+        if history.is_empty() {
+            return f64::NEG_INFINITY;
+        }
+
+        let backoff = self.ngrams[history.len() - 1].get(history).map(|ngram| ngram.backoff).unwrap_or(0f64);
+        backoff + self.ids_log10_prob(&history[1..], word)
+    }
+
+    pub fn prob(&self, history: &[&str], word: &str) -> f64 {
+        10f64.powf(self.log10_prob(history, word))
+    }
+
+    // sets the head and next word probabilities of dictionary words from the bigrams.
+    // the sentence boundary word stands for <s> as a history and for </s> as a next word,
+    // and may always start an utterance.
+    pub fn set_bigram_probs(&self, words: &mut [Word]) {
+        let names: Vec<(String, String)> = words.iter()
+            .map(|word| (history_name(word).to_string(), predicted_name(word).to_string()))
+            .collect();
+
+        for (w, word) in words.iter_mut().enumerate() {
+            word.head_prob = if word.is_sentence_boundary() {
+                1f64
+            } else {
+                self.prob(&[SENTENCE_START], &names[w].1)
+            };
+            for (next_w, (_, next_name)) in names.iter().enumerate() {
+                word.next_word_prob[next_w] = self.prob(&[&names[w].0], next_name);
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::ffi::OsStr;
+
+use error::{Error, Result};
+use fileutil;
+use lm::*;
+
+const MAX_ORDER: usize = 3;
+
+fn parse_log_prob(path: &str, line: usize, token: &str) -> Result<f64> {
+    token.parse().map_err(|_| Error::parse(path, line, token, "log10 probability"))
+}
+
+// reads an ARPA file of orders 1 to 3:
+// a \data\ section of "ngram n=count" lines, then a \n-grams: section for each order
+// of "log10_prob word.. [log10_backoff]" lines, and \end\
+pub fn read_arpa(path: &str) -> Result<LanguageModel> {
+    let lines = fileutil::read_lines(OsStr::new(path))?;
+    let n_lines = lines.len();
+    let mut lines = lines.iter().enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|&(_, line)| !line.is_empty());
+
+    match lines.next() {
+        Some((_, "\\data\\")) => (),
+        Some((number, line)) => return Err(Error::parse(path, number, line, "\\data\\")),
+        None => return Err(Error::parse(path, 1, "", "\\data\\")),
+    }
+
+    let mut counts: Vec<usize> = Vec::new();
+    let mut ngrams: Vec<Vec<(Vec<String>, NGram)>> = Vec::new();
+    let mut section_lines: Vec<usize> = Vec::new(); // line of each \n-grams: header
+    let mut order = 0; // of the section being read, 0 in \data\
+    let mut has_end = false;
+    for (number, line) in lines {
+        if line == "\\end\\" {
+            has_end = true;
+            break;
+        }
+
+        if line.starts_with('\\') {
+            let n = line.strip_prefix('\\')
+                .and_then(|line| line.strip_suffix("-grams:"))
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|&n| n == order + 1 && n <= counts.len())
+                .ok_or_else(|| Error::parse(path, number, line, &format!("\\{}-grams:", order + 1)))?;
+            order = n;
+            section_lines.push(number);
+            ngrams.push(Vec::with_capacity(counts[n - 1]));
+            continue;
+        }
+
+        if order == 0 {
+            // ngram n=count
+            let count = line.strip_prefix("ngram ")
+                .and_then(|line| {
+                    let mut parts = line.splitn(2, '=');
+                    match (parts.next(), parts.next()) {
+                        (Some(n), Some(count)) => Some((n.trim().parse::<usize>().ok()?, count.trim().parse::<usize>().ok()?)),
+                        _ => None,
+                    }
+                })
+                .filter(|&(n, _)| n == counts.len() + 1 && n <= MAX_ORDER)
+                .map(|(_, count)| count)
+                .ok_or_else(|| Error::parse(path, number, line, &format!("ngram {}=count up to order {}", counts.len() + 1, MAX_ORDER)))?;
+            counts.push(count);
+            continue;
+        }
+
+        let elements: Vec<&str> = line.split_whitespace().collect();
+        let backoff = match elements.len() {
+            n if n == order + 1 => 0f64,
+            n if n == order + 2 => parse_log_prob(path, number, elements[order + 1])?,
+            _ => return Err(Error::parse(path, number, line, &format!("log10 probability, {} words and optional backoff", order))),
+        };
+        let log_prob = parse_log_prob(path, number, elements[0])?;
+        let words = elements[1..order + 1].iter().map(|word| word.to_string()).collect();
+        ngrams[order - 1].push((words, NGram { log_prob, backoff }));
+    }
+
+    if !has_end {
+        return Err(Error::parse(path, n_lines, "", "\\end\\"));
+    }
+    if ngrams.len() != counts.len() || ngrams.is_empty() {
+        return Err(Error::parse(path, n_lines, "\\end\\", &format!("\\{}-grams:", ngrams.len() + 1)));
+    }
+    for (n, order_ngrams) in ngrams.iter().enumerate() {
+        if order_ngrams.len() != counts[n] {
+            let found = format!("{} entries", order_ngrams.len());
+            return Err(Error::parse(path, section_lines[n], &found, &format!("{} {}-grams as in \\data\\", counts[n], n + 1)));
+        }
+    }
+
+    Ok(LanguageModel::new(ngrams))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::*;
+
+    const TRIGRAMS: &str = "
+\\data\\
+ngram 1=5
+ngram 2=3
+ngram 3=1
+
+\\1-grams:
+-1.0 <s> -0.5
+-1.0 </s>
+-0.7 one -0.3
+-0.8 two -0.2
+-1.2 <unk>
+
+\\2-grams:
+-0.4 <s> one -0.1
+-0.3 one two -0.6
+-0.5 two </s>
+
+\\3-grams:
+-0.2 <s> one two
+
+\\end\\
+";
+
+    // reads ARPA text through a file of its own in the temporary directory
+    fn read(name: &str, text: &str) -> Result<LanguageModel> {
+        let path = env::temp_dir().join(format!("viterbi-rust-{}-{}.arpa", process::id(), name));
+        fs::write(&path, text).unwrap();
+        let model = read_arpa(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        model
+    }
+
+    fn assert_log_prob(model: &LanguageModel, history: &[&str], word: &str, expected: f64) {
+        let log_prob = model.log10_prob(history, word);
+        assert!((log_prob - expected).abs() < 1e-9, "log10 P({} | {:?}) = {}, expected {}", word, history, log_prob, expected);
+    }
+
+    fn parse_error(name: &str, text: &str) -> (usize, String) {
+        match read(name, text) {
+            Err(Error::Parse { line, expected, .. }) => (line, expected),
+            result => panic!("expected a parse error, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn seen_ngrams_have_their_own_probability() {
+        let model = read("seen", TRIGRAMS).unwrap();
+        assert_eq!(model.order(), 3);
+        assert_log_prob(&model, &["<s>", "one"], "two", -0.2);
+        assert_log_prob(&model, &["one"], "two", -0.3);
+        assert_log_prob(&model, &[], "two", -0.8);
+    }
+
+    #[test]
+    fn unseen_trigrams_back_off_to_bigrams() {
+        let model = read("bigram_backoff", TRIGRAMS).unwrap();
+        // backoff of "one two" and the bigram "two </s>"
+        assert_log_prob(&model, &["one", "two"], "</s>", -0.6 + -0.5);
+        // "two one" has no backoff weight of its own
+        assert_log_prob(&model, &["two", "one"], "two", -0.3);
+    }
+
+    #[test]
+    fn unseen_bigrams_back_off_to_unigrams() {
+        let model = read("unigram_backoff", TRIGRAMS).unwrap();
+        assert_log_prob(&model, &["two"], "one", -0.2 + -0.7);
+        // backoff of "one two", then of "two"
+        assert_log_prob(&model, &["one", "two"], "one", -0.6 + -0.2 + -0.7);
+        // histories longer than the model keep their latest words
+        assert_log_prob(&model, &["two", "<s>", "one"], "two", -0.2);
+    }
+
+    #[test]
+    fn words_out_of_the_vocabulary_are_unknown() {
+        let model = read("unknown", TRIGRAMS).unwrap();
+        assert_log_prob(&model, &["<s>"], "three", -0.5 + -1.2);
+        assert_log_prob(&model, &["three"], "two", -0.8);
+
+        let without_unknown = TRIGRAMS.replace("ngram 1=5", "ngram 1=4").replace("-1.2 <unk>\n", "");
+        let model = read("no_unknown", &without_unknown).unwrap();
+        assert_eq!(model.log10_prob(&["<s>"], "three"), f64::NEG_INFINITY);
+        assert_log_prob(&model, &["three"], "two", -0.8);
+    }
+
+    #[test]
+    fn counts_must_match_the_sections() {
+        let (line, expected) = parse_error("more_unigrams", &TRIGRAMS.replace("ngram 1=5", "ngram 1=6"));
+        assert_eq!((line, expected.as_str()), (7, "6 1-grams as in \\data\\"));
+
+        let (line, expected) = parse_error("fourth_order", &TRIGRAMS.replace("ngram 3=1", "ngram 3=1\nngram 4=1"));
+        assert_eq!((line, expected.as_str()), (6, "ngram 4=count up to order 3"));
+
+        let two_sections = TRIGRAMS.replace("\\3-grams:\n-0.2 <s> one two\n", "");
+        let (_, expected) = parse_error("missing_trigrams", &two_sections);
+        assert_eq!(expected, "\\3-grams:");
+    }
+
+    #[test]
+    fn data_counts_must_be_numbered_in_order() {
+        let (line, expected) = parse_error("unordered", &TRIGRAMS.replace("ngram 1=5\nngram 2=3", "ngram 2=3\nngram 1=5"));
+        assert_eq!((line, expected.as_str()), (3, "ngram 1=count up to order 3"));
+
+        let (line, _) = parse_error("not_a_count", &TRIGRAMS.replace("ngram 2=3", "ngram 2=three"));
+        assert_eq!(line, 4);
+
+        let (line, expected) = parse_error("no_data", &TRIGRAMS.replace("\\data\\", ""));
+        assert_eq!((line, expected.as_str()), (3, "\\data\\"));
+    }
+}
@@ -1,64 +1,61 @@
-extern crate tensorflow;
-
-mod fileutil;
-mod constants;
-mod phone;
-mod word;
-mod dnn;
-mod viterbi;
-
-use std::ffi::OsStr;
-use tensorflow::Result;
-use constants::PRINT_PERCENT_COUNT;
-
-fn get_rec_name(test_file_path: &OsStr) -> String {
-    let test_file_path = test_file_path.to_str().expect("Can't parse test file path");
-    let rec_name = str::replace(test_file_path, ".txt", ".rec");
-
-    format!("\"{}\"", &rec_name)
+extern crate viterbi_rust;
+
+mod cli;
+mod command;
+
+use std::env;
+use std::process;
+
+use cli::{Command, Matches};
+use command::COMMANDS;
+
+fn execute(command: &Command, args: &[String]) -> command::Result {
+    let matches = Matches::parse(command, args)?;
+    match command.name {
+        "run" => command::run::execute(&matches),
+        "decode" => command::decode::execute(&matches),
+        "score" => command::score::execute(&matches),
+        "beam-report" => command::beam_report::execute(&matches),
+        "priors" => command::priors::execute(&matches),
+        "tune" => command::tune::execute(&matches),
+        "align" => command::align::execute(&matches),
+        _ => unreachable!(),
+    }
 }
 
-fn run_all_tests() -> Result<()> {
-    let test_file_paths = fileutil::list_test_file_paths("tst");
-    let mut recognized_file = fileutil::create_file("recognized.txt");
-
-    let phones = phone::read_all("hmm.txt");
-    let words = word::read_all(
-        "dictionary.txt",
-        "unigram.txt",
-        "bigram.txt",
-        &phones,
-    );
-    let transitions = viterbi::wire_transitions(&words);
-    let mut dnn= dnn::load("dnn", &phones)?;
-
-    use std::io::Write;
-
-    let _ = recognized_file.write("#!MLF!#\n".as_bytes());
-
-    for (count, test_file_path) in test_file_paths.iter().enumerate() {
-        if count % PRINT_PERCENT_COUNT == 0 {
-            println!("{:.2}%..", count as f64 / test_file_paths.len() as f64 * 100f64);
-        }
-
-        let spectrogram = fileutil::read_spectrogram(test_file_path);
-        let rec_name = get_rec_name(test_file_path);
-        let _ = recognized_file.write_fmt(format_args!("{}\n", &rec_name));
-        let word_seq = viterbi::run(&spectrogram, &phones, &words, &transitions, &mut dnn);
+fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name).cloned()
+}
 
-        for word in word_seq.into_iter() {
-            if word.name == "<s>" {
-                continue;
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = args.first().map(|arg| arg.as_str()).unwrap_or("viterbi-rust");
+
+    let (command, args) = match args.get(1).map(|arg| arg.as_str()) {
+        None => (&command::run::COMMAND, &args[1..]),
+        Some(arg) if arg.starts_with("--") => (&command::run::COMMAND, &args[1..]),
+        Some("help") | Some("-h") => {
+            match args.get(2).and_then(|name| find_command(name)) {
+                Some(command) => print!("{}", cli::command_usage(program, command)),
+                None => print!("{}", cli::usage(program, COMMANDS)),
             }
-            let _ = recognized_file.write_fmt(format_args!("{}\n", &word.name));
+            return;
+        },
+        Some(name) => match find_command(name) {
+            Some(command) => (command, &args[2..]),
+            None => {
+                eprint!("unknown command '{}'\n\n{}", name, cli::usage(program, COMMANDS));
+                process::exit(2);
+            },
+        },
+    };
+
+    if let Err(error) = execute(command, args) {
+        eprintln!("error: {}", error);
+        if error.is::<cli::UsageError>() {
+            eprint!("\n{}", cli::command_usage(program, command));
+            process::exit(2);
         }
-        let _ = recognized_file.write(".\n".as_bytes());
+        process::exit(1);
     }
-
-    println!("100%");
-    Ok(())
-}
-
-fn main() -> Result<()> {
-    run_all_tests()
 }
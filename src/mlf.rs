@@ -0,0 +1,158 @@
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::Path;
+
+use constants::FRAME_PERIOD;
+use error::Result;
+use fileutil;
+
+pub const HEADER: &str = "#!MLF!#";
+pub const ALTERNATIVE_SEPARATOR: &str = "///";
+
+// a "start end name score [confidence]" label line, with times in HTK units of 100ns
+#[derive(Clone, Debug)]
+pub struct Label<'a> {
+    pub start: u64,
+    pub end: u64,
+    pub name: &'a str,
+    pub score: f64,
+    pub confidence: Option<f64>,
+}
+
+impl<'a> Label<'a> {
+    // label of the frames from start_frame to end_frame inclusive
+    pub fn of_frames(name: &'a str, start_frame: usize, end_frame: usize, score: f64) -> Label<'a> {
+        Label {
+            start: start_frame as u64 * FRAME_PERIOD,
+            end: (end_frame + 1) as u64 * FRAME_PERIOD,
+            name,
+            score,
+            confidence: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Transcription {
+    pub name: String,
+    pub words: Vec<String>,
+}
+
+impl Transcription {
+    // name without quotes and extension, so "tst/f/ak/1237743.lab" and
+    // "tst/f/ak/1237743.rec" refer to the same utterance.
+    pub fn key(&self) -> String {
+        utterance_key(&self.name)
+    }
+}
+
+pub fn utterance_key(name: &str) -> String {
+    let name = name.trim_matches('"');
+    let path = Path::new(name);
+    match path.extension() {
+        Some(_) => path.with_extension("").to_string_lossy().into_owned(),
+        None => name.to_string(),
+    }
+}
+
+// reads the first alternative of every transcription
+pub fn read(mlf_path: &str) -> Result<Vec<Transcription>> {
+    let lines = fileutil::read_lines(OsStr::new(mlf_path))?;
+
+    let mut transcriptions = Vec::new();
+    let mut current: Option<Transcription> = None;
+    let mut is_alternative = false;
+    for line in lines.iter() {
+        let line = line.trim();
+        if line.is_empty() || line == HEADER {
+            continue;
+        }
+
+        current = match current {
+            None => {
+                is_alternative = false;
+                Some(Transcription { name: line.trim_matches('"').to_string(), words: Vec::new() })
+            },
+            Some(mut transcription) => {
+                if line == "." {
+                    transcriptions.push(transcription);
+                    None
+                } else {
+                    if line == ALTERNATIVE_SEPARATOR {
+                        is_alternative = true;
+                    } else if !is_alternative {
+                        transcription.words.push(read_label(line));
+                    }
+                    Some(transcription)
+                }
+            }
+        };
+    }
+
+    if let Some(transcription) = current {
+        transcriptions.push(transcription);
+    }
+
+    Ok(transcriptions)
+}
+
+// a label line is either "word [confidence]" or "start end word [score..]"
+fn read_label(line: &str) -> String {
+    let elements: Vec<&str> = line.split_whitespace().collect();
+    let has_times = elements.len() >= 3
+        && elements[0].parse::<u64>().is_ok()
+        && elements[1].parse::<u64>().is_ok();
+
+    if has_times {
+        elements[2].to_string()
+    } else {
+        elements[0].to_string()
+    }
+}
+
+pub fn write_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "{}", HEADER)
+}
+
+pub fn write_transcription<W: Write>(writer: &mut W, name: &str, words: &[&str]) -> io::Result<()> {
+    writeln!(writer, "\"{}\"", name)?;
+    for word in words.iter() {
+        writeln!(writer, "{}", word)?;
+    }
+    writeln!(writer, ".")
+}
+
+pub fn write_labels<W: Write>(writer: &mut W, name: &str, labels: &[Label]) -> io::Result<()> {
+    writeln!(writer, "\"{}\"", name)?;
+    for label in labels.iter() {
+        write!(writer, "{} {} {} {:.4}", label.start, label.end, label.name, label.score)?;
+        if let Some(confidence) = label.confidence {
+            write!(writer, " {:.4}", confidence)?;
+        }
+        writeln!(writer)?;
+    }
+    writeln!(writer, ".")
+}
+
+// "word confidence" lines
+pub fn write_confidences<W: Write>(writer: &mut W, name: &str, words: &[(&str, f64)]) -> io::Result<()> {
+    writeln!(writer, "\"{}\"", name)?;
+    for &(word, confidence) in words.iter() {
+        writeln!(writer, "{} {:.4}", word, confidence)?;
+    }
+    writeln!(writer, ".")
+}
+
+// N-best transcriptions, best first, separated by "///"
+pub fn write_alternatives<W: Write>(writer: &mut W, name: &str, alternatives: &[Vec<&str>]) -> io::Result<()> {
+    writeln!(writer, "\"{}\"", name)?;
+    for (rank, words) in alternatives.iter().enumerate() {
+        if rank > 0 {
+            writeln!(writer, "{}", ALTERNATIVE_SEPARATOR)?;
+        }
+        for word in words.iter() {
+            writeln!(writer, "{}", word)?;
+        }
+    }
+    writeln!(writer, ".")
+}
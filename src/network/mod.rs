@@ -0,0 +1,245 @@
+pub mod read;
+
+pub use self::read::read_slf;
+
+use std::collections::HashMap;
+
+use word::Word;
+use lm::{self, LanguageModel, SENTENCE_START};
+
+// a word in some language model context
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub word: usize, // index into the dictionary words
+    pub head_prob: f64, // probability of starting an utterance here
+    pub next: Vec<(usize, f64)>, // nodes which may follow and their probabilities, by node index
+    pub is_final: bool, // the utterance may end in this node
+}
+
+// the words the search may pass through and the language model between them.
+// states of the search belong to nodes, so tokens meeting in a state share the node's context.
+#[derive(Clone, Debug)]
+pub struct Network {
+    pub nodes: Vec<Node>,
+}
+
+// one node per word with its head and next word probabilities
+pub fn bigram(words: &[Word]) -> Network {
+    let nodes = words.iter().enumerate()
+        .map(|(w, word)| Node {
+            word: w,
+            head_prob: word.head_prob,
+            next: word.next_word_prob.iter().cloned().enumerate()
+                .filter(|&(_, prob)| prob > 0f64)
+                .collect(),
+            is_final: true,
+        })
+        .collect();
+
+    Network { nodes }
+}
+
+// the network with a node for each node and number of words before it, which may only end
+// once min_words to max_words words were said. silences and sentence boundaries are not counted,
+// nor is the word the utterance ends in, which is left out of the result.
+// without max_words, counts stop at min_words. nodes no path from the start reaches are dropped.
+pub fn count_words(network: &Network, words: &[Word], min_words: usize, max_words: Option<usize>) -> Network {
+    let counts_word = |node: &Node| {
+        let word = &words[node.word];
+        !word.is_sentence_boundary() && !word.is_silence()
+    };
+
+    // (node, count) of each node reached, in the order found
+    let mut keys: Vec<(usize, usize)> = (0..network.nodes.len())
+        .filter(|&n| network.nodes[n].head_prob > 0f64)
+        .map(|n| (n, 0))
+        .collect();
+    let mut node_index: HashMap<(usize, usize), usize> = keys.iter().enumerate()
+        .map(|(index, &key)| (key, index))
+        .collect();
+    let mut nodes = Vec::new();
+    while nodes.len() < keys.len() {
+        let (n, count) = keys[nodes.len()];
+        let node = &network.nodes[n];
+        let next_count = match (counts_word(node), max_words) {
+            (false, _) => count,
+            (true, Some(_)) => count + 1,
+            (true, None) => (count + 1).min(min_words),
+        };
+        let mut next = Vec::new();
+        if max_words.is_none_or(|max_words| next_count <= max_words) {
+            for &(next_n, prob) in node.next.iter() {
+                let key = (next_n, next_count);
+                let index = *node_index.entry(key).or_insert_with(|| {
+                    keys.push(key);
+                    keys.len() - 1
+                });
+                next.push((index, prob));
+            }
+        }
+
+        nodes.push(Node {
+            word: node.word,
+            head_prob: if count == 0 { node.head_prob } else { 0f64 },
+            next,
+            is_final: node.is_final && min_words <= count && max_words.is_none_or(|max_words| count <= max_words),
+        });
+    }
+
+    Network { nodes }
+}
+
+// one node per word and the word before it, so that transitions carry trigram probabilities.
+// the word before is told by its name, and is <s> at the start and after a sentence boundary,
+// which forgets the history and so has a single node.
+pub fn trigram(words: &[Word], lm: &LanguageModel) -> Network {
+    let mut contexts: Vec<&str> = vec![SENTENCE_START];
+    for word in words.iter() {
+        if !word.is_sentence_boundary() && !contexts.contains(&word.name.as_str()) {
+            contexts.push(&word.name);
+        }
+    }
+
+    let mut keys: Vec<(usize, usize)> = Vec::new(); // (context, word) of each node
+    for (w, word) in words.iter().enumerate() {
+        if word.is_sentence_boundary() {
+            keys.push((0, w));
+        } else {
+            keys.extend((0..contexts.len()).map(|c| (c, w)));
+        }
+    }
+    let node_index: HashMap<(usize, usize), usize> = keys.iter().enumerate()
+        .map(|(n, &key)| (key, n))
+        .collect();
+
+    let nodes = keys.iter()
+        .map(|&(c, w)| {
+            let word = &words[w];
+            let (history, next_context) = if word.is_sentence_boundary() {
+                (vec![SENTENCE_START], 0)
+            } else {
+                let next_context = contexts.iter().position(|&context| context == word.name).expect("Context");
+                (vec![contexts[c], word.name.as_str()], next_context)
+            };
+
+            let head_prob = if c != 0 {
+                0f64
+            } else if word.is_sentence_boundary() {
+                1f64
+            } else {
+                lm.prob(&[SENTENCE_START], lm::predicted_name(word))
+            };
+            let next = words.iter().enumerate()
+                .map(|(next_w, next_word)| {
+                    let next_c = if next_word.is_sentence_boundary() { 0 } else { next_context };
+                    (node_index[&(next_c, next_w)], lm.prob(&history, lm::predicted_name(next_word)))
+                })
+                .filter(|&(_, prob)| prob > 0f64)
+                .collect();
+
+            Node { word: w, head_prob, next, is_final: true }
+        })
+        .collect();
+
+    Network { nodes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phone::Phone;
+
+    fn phone(name: &str) -> Phone {
+        Phone {
+            index: 0,
+            name: name.to_string(),
+            n_states: 1,
+            in_prob: vec![1f64],
+            trans_prob: vec![vec![0.5]],
+            out_prob: vec![0.5],
+            skip_prob: 0f64,
+        }
+    }
+
+    // <s>, a silence and two digits, each word followed by any other
+    fn words(phones: &[Phone]) -> Vec<Word<'_>> {
+        let prons: &[(&str, &[usize])] = &[("<s>", &[0]), ("pause", &[1]), ("one", &[2, 1]), ("two", &[3, 1])];
+        prons.iter()
+            .map(|&(name, indices)| Word {
+                name: name.to_string(),
+                phones: indices.iter().map(|&index| &phones[index]).collect(),
+                head_prob: 0.25,
+                next_word_prob: vec![0.25; prons.len()],
+            })
+            .collect()
+    }
+
+    // the count of every node reached from the heads, which must be the same on every way there.
+    // counts stop at limit.
+    fn node_counts(network: &Network, words: &[Word], limit: usize) -> Vec<Option<usize>> {
+        let mut counts = vec![None; network.nodes.len()];
+        let mut pending: Vec<(usize, usize)> = (0..network.nodes.len())
+            .filter(|&n| network.nodes[n].head_prob > 0f64)
+            .map(|n| (n, 0))
+            .collect();
+        while let Some((n, count)) = pending.pop() {
+            match counts[n] {
+                Some(found) => assert_eq!(found, count, "node {} reached with two counts", n),
+                None => {
+                    counts[n] = Some(count);
+                    let word = &words[network.nodes[n].word];
+                    let next_count = if word.is_sentence_boundary() || word.is_silence() { count } else { (count + 1).min(limit) };
+                    pending.extend(network.nodes[n].next.iter().map(|&(next, _)| (next, next_count)));
+                },
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn only_counts_in_range_are_final() {
+        let phones = vec![phone("sil"), phone("sp"), phone("w"), phone("t")];
+        let words = words(&phones);
+        let network = count_words(&bigram(&words), &words, 1, Some(2));
+
+        let counts = node_counts(&network, &words, usize::MAX);
+        for (node, count) in network.nodes.iter().zip(counts.iter()) {
+            let count = count.expect("every node is reached");
+            assert!(count <= 2);
+            assert_eq!(node.is_final, (1..=2).contains(&count));
+        }
+        for count in 0..3 {
+            assert!(counts.contains(&Some(count)));
+        }
+    }
+
+    #[test]
+    fn counts_stop_growing_past_max() {
+        let phones = vec![phone("sil"), phone("sp"), phone("w"), phone("t")];
+        let words = words(&phones);
+        let network = count_words(&bigram(&words), &words, 0, Some(1));
+
+        let counts = node_counts(&network, &words, usize::MAX);
+        for (node, count) in network.nodes.iter().zip(counts.iter()) {
+            let word = &words[node.word];
+            if count == &Some(1) && !word.is_sentence_boundary() && !word.is_silence() {
+                assert!(node.next.is_empty(), "a second counted word follows {}", word.name);
+            }
+        }
+        // a node per word at count 0, and per word at count 1
+        assert_eq!(network.nodes.len(), 2 * words.len());
+    }
+
+    #[test]
+    fn counts_without_max_stop_at_min() {
+        let phones = vec![phone("sil"), phone("sp"), phone("w"), phone("t")];
+        let words = words(&phones);
+        let network = count_words(&bigram(&words), &words, 2, None);
+
+        let counts = node_counts(&network, &words, 2);
+        assert!(counts.iter().all(|&count| count.is_some_and(|count| count <= 2)));
+        for (node, count) in network.nodes.iter().zip(counts.iter()) {
+            assert_eq!(node.is_final, count == &Some(2));
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::f64;
+use std::ffi::OsStr;
+
+use error::{Error, Result};
+use fileutil;
+use word::Word;
+use network::{Network, Node};
+
+const NULL_WORD: &str = "!NULL";
+
+// a link of the word network as read, with its probability
+#[derive(Clone, Copy, Debug)]
+struct Link {
+    start: usize,
+    end: usize,
+    prob: f64,
+}
+
+// name=value fields of a line
+fn fields<'a>(path: &str, number: usize, line: &'a str) -> Result<Vec<(&'a str, &'a str)>> {
+    line.split_whitespace()
+        .map(|field| {
+            let mut parts = field.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => Ok((name, value)),
+                _ => Err(Error::parse(path, number, field, "name=value field")),
+            }
+        })
+        .collect()
+}
+
+fn field<'a>(fields: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    fields.iter().find(|&&(field_name, _)| field_name == name).map(|&(_, value)| value)
+}
+
+fn parse_index(path: &str, number: usize, value: &str, count: Option<usize>, what: &str) -> Result<usize> {
+    value.parse::<usize>().ok()
+        .filter(|&index| index < count.unwrap_or(0))
+        .ok_or_else(|| Error::parse(path, number, value, &format!("{} index below the count in the header", what)))
+}
+
+// the single node without links into it (or out of it), unless the header names one
+fn terminal_node(path: &str, n_lines: usize, named: Option<usize>, has_links: &[bool], expected: &str) -> Result<usize> {
+    if let Some(node) = named {
+        return Ok(node);
+    }
+    let mut candidates = (0..has_links.len()).filter(|&node| !has_links[node]);
+    match (candidates.next(), candidates.next()) {
+        (Some(node), None) => Ok(node),
+        _ => Err(Error::parse(path, n_lines, "", expected)),
+    }
+}
+
+// nodes reached from a node through null nodes only, with the probability of the best way there
+fn reach(from: usize, successors: &[Vec<(usize, f64)>], names: &[Option<&str>]) -> Vec<(usize, f64)> {
+    let mut best: HashMap<usize, f64> = HashMap::new();
+    let mut pending = successors[from].clone();
+    while let Some((node, prob)) = pending.pop() {
+        if best.get(&node).is_some_and(|&found| found >= prob) {
+            continue;
+        }
+        best.insert(node, prob);
+        if names[node].is_none() {
+            pending.extend(successors[node].iter().map(|&(next, link_prob)| (next, prob * link_prob)));
+        }
+    }
+
+    let mut reached: Vec<(usize, f64)> = best.into_iter().collect();
+    reached.sort_by_key(|&(node, _)| node);
+    reached
+}
+
+// reads a word network in HTK Standard Lattice Format, as written by HParse or HBuild:
+// a header with N=nodes and L=links, then I=index W=word lines and J=index S=start E=end [l=log_prob] lines.
+// words may be given on nodes or on links, and !NULL nodes join words without being one.
+// l= is a natural log unless the header sets base=, and links without it have probability 1.
+// the network starts at start= (or the node without links into it) and may end in any word
+// from which end= (or the node without links out of it) is reached through null nodes.
+// a word with several pronunciations gets a node for each.
+pub fn read_slf(path: &str, words: &[Word]) -> Result<Network> {
+    let lines = fileutil::read_lines(OsStr::new(path))?;
+    let n_lines = lines.len();
+
+    let mut n_nodes: Option<usize> = None;
+    let mut n_links: Option<usize> = None;
+    let mut base = f64::consts::E;
+    let mut start_node: Option<&str> = None;
+    let mut end_node: Option<&str> = None;
+    let mut nodes: Vec<Option<(Option<&str>, usize)>> = Vec::new(); // word and line of each node once read
+    let mut links: Vec<Link> = Vec::new();
+    let mut link_words: Vec<(usize, &str, usize)> = Vec::new(); // link, word and line of words given on links
+    for (number, line) in lines.iter().enumerate() {
+        let number = number + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = fields(path, number, line)?;
+        match fields[0].0 {
+            "I" => {
+                let index = parse_index(path, number, fields[0].1, n_nodes, "node")?;
+                let word = field(&fields, "W").filter(|&word| word != NULL_WORD);
+                nodes[index] = Some((word, number));
+            },
+            "J" => {
+                let node_index = |name: &str| {
+                    let value = field(&fields, name).ok_or_else(|| Error::parse(path, number, line, &format!("{}=node", name)))?;
+                    parse_index(path, number, value, n_nodes, "node")
+                };
+                let start = node_index("S")?;
+                let end = node_index("E")?;
+                let prob = match field(&fields, "l") {
+                    Some(value) => {
+                        let log_prob: f64 = value.parse().map_err(|_| Error::parse(path, number, value, "log probability"))?;
+                        base.powf(log_prob)
+                    },
+                    None => 1f64,
+                };
+                if prob.is_nan() || prob > 1f64 {
+                    return Err(Error::parse(path, number, field(&fields, "l").unwrap_or(""), "log probability at most 0"));
+                }
+
+                if let Some(word) = field(&fields, "W").filter(|&word| word != NULL_WORD) {
+                    link_words.push((links.len(), word, number));
+                }
+                links.push(Link { start, end, prob });
+            },
+            _ => {
+                for &(name, value) in fields.iter() {
+                    match name {
+                        "N" | "NODES" => {
+                            let count = value.parse().map_err(|_| Error::parse(path, number, value, "node count"))?;
+                            n_nodes = Some(count);
+                            nodes = vec![None; count];
+                        },
+                        "L" | "LINKS" => n_links = Some(value.parse().map_err(|_| Error::parse(path, number, value, "link count"))?),
+                        "base" => base = value.parse().map_err(|_| Error::parse(path, number, value, "log base"))?,
+                        "start" => start_node = Some(value),
+                        "end" => end_node = Some(value),
+                        _ => (),
+                    }
+                }
+            },
+        }
+    }
+
+    if n_links != Some(links.len()) {
+        let found = format!("{} links", links.len());
+        return Err(Error::parse(path, n_lines, &found, "as many J= lines as L= in the header"));
+    }
+    let mut names: Vec<Option<&str>> = Vec::with_capacity(nodes.len() + link_words.len());
+    let mut node_lines: Vec<usize> = Vec::with_capacity(nodes.len() + link_words.len());
+    for (index, node) in nodes.iter().enumerate() {
+        match *node {
+            Some((word, line)) => {
+                names.push(word);
+                node_lines.push(line);
+            },
+            None => return Err(Error::parse(path, n_lines, "", &format!("I={} node line", index))),
+        }
+    }
+    let start = match start_node {
+        Some(value) => Some(parse_index(path, n_lines, value, n_nodes, "start node")?),
+        None => None,
+    };
+    let end = match end_node {
+        Some(value) => Some(parse_index(path, n_lines, value, n_nodes, "end node")?),
+        None => None,
+    };
+
+    // a word on a link becomes a node between its start and end
+    for &(link, word, line) in link_words.iter() {
+        let word_node = names.len();
+        names.push(Some(word));
+        node_lines.push(line);
+        let end = links[link].end;
+        links[link].end = word_node;
+        links.push(Link { start: word_node, end, prob: 1f64 });
+    }
+
+    let mut has_links_in = vec![false; names.len()];
+    let mut has_links_out = vec![false; names.len()];
+    let mut successors: Vec<Vec<(usize, f64)>> = vec![Vec::new(); names.len()];
+    for link in links.iter() {
+        has_links_out[link.start] = true;
+        has_links_in[link.end] = true;
+        successors[link.start].push((link.end, link.prob));
+    }
+    let start = terminal_node(path, n_lines, start, &has_links_in, "start=node, or a single node without links into it")?;
+    let end = terminal_node(path, n_lines, end, &has_links_out, "end=node, or a single node without links out of it")?;
+
+    // dictionary words of each network word, one per pronunciation
+    let mut network_nodes: Vec<Vec<usize>> = Vec::with_capacity(names.len());
+    let mut node_words: Vec<usize> = Vec::new();
+    for (node, name) in names.iter().enumerate() {
+        let variants: Vec<usize> = match *name {
+            Some(name) => {
+                let variants: Vec<usize> = (0..words.len()).filter(|&w| words[w].name == name).collect();
+                if variants.is_empty() {
+                    return Err(Error::parse(path, node_lines[node], name, "dictionary word or !NULL"));
+                }
+                variants
+            },
+            None => Vec::new(),
+        };
+        network_nodes.push((node_words.len()..node_words.len() + variants.len()).collect());
+        node_words.extend(variants);
+    }
+
+    let heads: Vec<(usize, f64)> = if names[start].is_some() {
+        vec![(start, 1f64)]
+    } else {
+        reach(start, &successors, &names)
+    };
+    let mut network = Network { nodes: Vec::with_capacity(node_words.len()) };
+    for (node, name) in names.iter().enumerate() {
+        if name.is_none() {
+            continue;
+        }
+        let reached = reach(node, &successors, &names);
+        let head_prob = heads.iter().find(|&&(head, _)| head == node).map_or(0f64, |&(_, prob)| prob);
+        let next: Vec<(usize, f64)> = reached.iter()
+            .flat_map(|&(next, prob)| network_nodes[next].iter().map(move |&next_node| (next_node, prob)))
+            .collect();
+        let is_final = node == end || (names[end].is_none() && reached.iter().any(|&(next, _)| next == end));
+
+        for &w in network_nodes[node].iter() {
+            network.nodes.push(Node { word: node_words[w], head_prob, next: next.clone(), is_final });
+        }
+    }
+
+    if network.nodes.iter().all(|node| node.head_prob == 0f64) {
+        return Err(Error::parse(path, n_lines, "", "a word reached from the start node"));
+    }
+    if network.nodes.iter().all(|node| !node.is_final) {
+        return Err(Error::parse(path, n_lines, "", "a word from which the end node is reached"));
+    }
+    Ok(network)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::*;
+
+    // the words of the networks, where "two" has two pronunciations. read_slf only needs their names.
+    fn words() -> Vec<Word<'static>> {
+        ["<s>", "one", "two", "two"].iter()
+            .map(|name| Word { name: name.to_string(), phones: Vec::new(), head_prob: 0.25, next_word_prob: vec![0.25; 4] })
+            .collect()
+    }
+
+    // reads SLF text through a file of its own in the temporary directory
+    fn read(name: &str, text: &str) -> Result<Network> {
+        let path = env::temp_dir().join(format!("viterbi-rust-{}-{}.slf", process::id(), name));
+        fs::write(&path, text).unwrap();
+        let network = read_slf(path.to_str().unwrap(), &words());
+        fs::remove_file(&path).unwrap();
+        network
+    }
+
+    fn assert_node(network: &Network, n: usize, word: usize, head_prob: f64, next: &[(usize, f64)], is_final: bool) {
+        let node = &network.nodes[n];
+        assert_eq!((node.word, node.head_prob, &node.next[..], node.is_final), (word, head_prob, next, is_final), "node {}", n);
+    }
+
+    fn parse_error(name: &str, text: &str) -> (usize, String, String) {
+        match read(name, text) {
+            Err(Error::Parse { line, token, expected, .. }) => (line, token, expected),
+            result => panic!("expected a parse error, found {:?}", result),
+        }
+    }
+
+    const WORDS_ON_NODES: &str = "VERSION=1.0
+N=4 L=3
+I=0 W=!NULL
+I=1 W=one
+I=2 W=two
+I=3 W=!NULL
+J=0 S=0 E=1
+J=1 S=1 E=2 l=-0.5
+J=2 S=2 E=3
+";
+
+    #[test]
+    fn words_on_nodes() {
+        let network = read("nodes", WORDS_ON_NODES).unwrap();
+        assert_eq!(network.nodes.len(), 3);
+        let prob = (-0.5f64).exp();
+        assert_node(&network, 0, 1, 1f64, &[(1, prob), (2, prob)], false);
+        assert_node(&network, 1, 2, 0f64, &[], true);
+        assert_node(&network, 2, 3, 0f64, &[], true);
+    }
+
+    #[test]
+    fn words_on_links() {
+        let text = "N=3 L=2
+I=0
+I=1 W=!NULL
+I=2
+J=0 S=0 E=1 W=one
+J=1 S=1 E=2 W=two l=-0.5
+";
+        let network = read("links", text).unwrap();
+        assert_eq!(network.nodes.len(), 3);
+        assert_node(&network, 0, 1, 1f64, &[(1, (-0.5f64).exp()), (2, (-0.5f64).exp())], false);
+        assert_node(&network, 1, 2, 0f64, &[], true);
+        assert_node(&network, 2, 3, 0f64, &[], true);
+    }
+
+    #[test]
+    fn words_are_reached_through_null_nodes() {
+        // start and end are null nodes, and null node 2 joins the start and "one" to "<s>"
+        let text = "N=5 L=6 base=2
+I=0 W=!NULL
+I=1 W=one
+I=2 W=!NULL
+I=3 W=<s>
+I=4 W=!NULL
+J=0 S=0 E=1 l=-1
+J=1 S=0 E=2 l=-1
+J=2 S=2 E=3 l=-1
+J=3 S=1 E=2
+J=4 S=3 E=4
+J=5 S=1 E=4
+";
+        let network = read("null", text).unwrap();
+        assert_eq!(network.nodes.len(), 2);
+        assert_node(&network, 0, 1, 0.5, &[(1, 0.5)], true);
+        assert_node(&network, 1, 0, 0.25, &[], true);
+    }
+
+    #[test]
+    fn word_nodes_may_start_and_end() {
+        let text = "N=2 L=1
+I=0 W=one
+I=1 W=<s>
+J=0 S=0 E=1
+";
+        let network = read("word_ends", text).unwrap();
+        assert_node(&network, 0, 1, 1f64, &[(1, 1f64)], false);
+        assert_node(&network, 1, 0, 0f64, &[], true);
+    }
+
+    #[test]
+    fn start_and_end_are_found_or_named() {
+        let with_island = WORDS_ON_NODES.replace("N=4", "N=5") + "I=4 W=!NULL\n";
+        let (line, _, expected) = parse_error("two_starts", &with_island);
+        assert_eq!((line, expected.as_str()), (10, "start=node, or a single node without links into it"));
+
+        let named_start = with_island.replace("N=5", "N=5 start=0");
+        let (_, _, expected) = parse_error("two_ends", &named_start);
+        assert_eq!(expected, "end=node, or a single node without links out of it");
+
+        let network = read("named", &named_start.replace("N=5", "N=5 end=3")).unwrap();
+        assert_eq!(network.nodes.len(), 3);
+        assert!(network.nodes[1].is_final && !network.nodes[0].is_final);
+
+        // "one" ends the network, so "two" may not end an utterance
+        let network = read("named_word_end", &named_start.replace("N=5", "N=5 end=1")).unwrap();
+        assert!(network.nodes[0].is_final && !network.nodes[1].is_final);
+    }
+
+    #[test]
+    fn unknown_words_are_errors() {
+        let (line, token, expected) = parse_error("unknown_node", &WORDS_ON_NODES.replace("I=2 W=two", "I=2 W=three"));
+        assert_eq!((line, token.as_str(), expected.as_str()), (5, "three", "dictionary word or !NULL"));
+
+        let text = "N=2 L=1
+I=0
+I=1
+J=0 S=0 E=1 W=three
+";
+        let (line, token, expected) = parse_error("unknown_link", text);
+        assert_eq!((line, token.as_str(), expected.as_str()), (4, "three", "dictionary word or !NULL"));
+    }
+}
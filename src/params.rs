@@ -0,0 +1,108 @@
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::Write;
+
+use constants::*;
+use error::{Error, Result};
+use fileutil;
+
+// weights combining acoustic and language model scores in the search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Params {
+    pub acoustic_scale: f64, // multiplies the log scaled likelihood of every frame
+    pub lm_scale: f64, // multiplies the log probability of every word transition
+    pub word_penalty: f64, // subtracted at every word transition
+    pub prior_scale: f64, // exponent of the label priors dividing the posteriors
+}
+
+pub const NAMES: &[&str] = &["acoustic-scale", "lm-scale", "word-penalty", "prior-scale"];
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            acoustic_scale: 1f64,
+            lm_scale: 1f64,
+            word_penalty: WORD_PENALTY,
+            prior_scale: 1f64,
+        }
+    }
+}
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<f64> {
+        match name {
+            "acoustic-scale" => Some(self.acoustic_scale),
+            "lm-scale" => Some(self.lm_scale),
+            "word-penalty" => Some(self.word_penalty),
+            "prior-scale" => Some(self.prior_scale),
+            _ => None,
+        }
+    }
+
+    // returns false for an unknown name
+    pub fn set(&mut self, name: &str, value: f64) -> bool {
+        match name {
+            "acoustic-scale" => self.acoustic_scale = value,
+            "lm-scale" => self.lm_scale = value,
+            "word-penalty" => self.word_penalty = value,
+            "prior-scale" => self.prior_scale = value,
+            _ => return false,
+        }
+        true
+    }
+}
+
+// what a parameter takes when the value is not one of them, or None for a valid value.
+// values must be finite, and the acoustic and confidence scales positive, so that no score becomes NaN.
+pub fn expected(name: &str, value: f64) -> Option<&'static str> {
+    if !value.is_finite() {
+        Some("finite number")
+    } else if (name == "acoustic-scale" || name == "confidence-scale") && value <= 0f64 {
+        Some("number above 0")
+    } else {
+        None
+    }
+}
+
+// one "name value" line per parameter, as read by `read`
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for name in NAMES.iter() {
+            writeln!(f, "{} {}", name, self.get(name).unwrap())?;
+        }
+        Ok(())
+    }
+}
+
+// reads "name value" lines over the defaults; '#' starts a comment
+pub fn read(path: &str) -> Result<Params> {
+    let lines = fileutil::read_lines(OsStr::new(path))?;
+
+    let mut params = Params::default();
+    for (number, line) in lines.iter().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let elements: Vec<&str> = line.split_whitespace().collect();
+
+        match elements.as_slice() {
+            [] => (),
+            [name, value] => {
+                let token = value;
+                let value: f64 = value.parse().map_err(|_| Error::parse(path, number + 1, token, "number"))?;
+                if !params.set(name, value) {
+                    return Err(Error::parse(path, number + 1, name, &format!("one of {}", NAMES.join(", "))));
+                }
+                if let Some(expected) = expected(name, value) {
+                    return Err(Error::parse(path, number + 1, token, expected));
+                }
+            },
+            _ => return Err(Error::parse(path, number + 1, line.trim(), "parameter name and value")),
+        }
+    }
+
+    Ok(params)
+}
+
+pub fn write(path: &str, params: &Params) -> Result<()> {
+    let mut file = fileutil::create_file(path)?;
+    write!(file, "{}", params).map_err(|error| Error::io(path, error))
+}
@@ -13,12 +13,7 @@ pub struct Phone {
     pub skip_prob: f64, // transition prob between entry and exit
 }
 
-pub fn find<'a>(name: &str, phones: &'a [Phone]) -> &'a Phone {
-    let find_result = phones.iter()
-        .find(|phone| phone.name == name);
-
-    match find_result {
-        Some(phone) => phone,
-        None => panic!("No phone name: {}", name)
-    }
+pub fn find<'a>(name: &str, phones: &'a [Phone]) -> Option<&'a Phone> {
+    phones.iter()
+        .find(|phone| phone.name == name)
 }
@@ -1,3 +1,6 @@
+use std::ffi::OsStr;
+
+use error::{Error, Result};
 use fileutil;
 use phone::*;
 
@@ -12,111 +15,155 @@ enum InputType {
 
 use self::InputType::*;
 
-fn split_whitespace(line: &String) -> Vec<&str> {
+// lines of an hmm file which are not read yet
+struct Lines<'a> {
+    path: &'a str,
+    lines: &'a [String],
+    position: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn is_empty(&mut self) -> bool {
+        self.skip_blank();
+        self.position >= self.lines.len()
+    }
+
+    fn next(&mut self, expected: &str) -> Result<&'a String> {
+        self.skip_blank();
+        let line = self.lines.get(self.position)
+            .ok_or_else(|| Error::parse(self.path, self.position + 1, "", expected))?;
+        self.position += 1;
+        Ok(line)
+    }
+
+    fn skip_blank(&mut self) {
+        while self.position < self.lines.len() && self.lines[self.position].trim().is_empty() {
+            self.position += 1;
+        }
+    }
+
+    // error at the line returned by the last next()
+    fn error(&self, token: &str, expected: &str) -> Error {
+        Error::parse(self.path, self.position, token, expected)
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        let token = self.lines[self.position - 1].split_whitespace().next().unwrap_or("");
+        self.error(token, expected)
+    }
+}
+
+fn split_whitespace(line: &str) -> Vec<&str> {
     line.split_whitespace()
         .collect()
 }
 
-fn get_float_values(line: &String) -> Vec<f64> {
+fn parse_value<T: std::str::FromStr>(lines: &Lines, value: Option<&&str>, expected: &str) -> Result<T> {
+    let value = value.cloned().unwrap_or("");
+    value.parse().map_err(|_| lines.error(value, expected))
+}
+
+fn get_float_values(lines: &Lines, line: &str) -> Result<Vec<f64>> {
     split_whitespace(line).into_iter()
-        .map(|s| s.parse().expect("Float Value"))
+        .map(|s| s.parse().map_err(|_| lines.error(s, "float value")))
         .collect()
 }
 
 // reads input from lines and return InputType
 // it consumes read lines.
-fn read_one_input(lines: &mut &[String]) -> InputType {
-    let values = split_whitespace(&lines[0]);
+fn read_one_input(lines: &mut Lines) -> Result<InputType> {
+    let values = split_whitespace(lines.next("hmm definition")?);
 
     match values[0] {
         "~h" => {
-            let phone_name = values[1][1..values[1].len() - 1].to_string();
-            *lines = &lines[1..];
-            PhoneName(phone_name)
-        },
-        "<BEGINHMM>" => {
-            *lines = &lines[1..];
-            BeginHmm
-        },
-        "<ENDHMM>" => {
-            *lines = &lines[1..];
-            EndHmm 
+            let quoted = values.get(1).cloned().unwrap_or("");
+            if quoted.len() < 2 || !quoted.starts_with('"') || !quoted.ends_with('"') {
+                return Err(lines.error(quoted, "quoted phone name"));
+            }
+            Ok(PhoneName(quoted[1..quoted.len() - 1].to_string()))
         },
+        "<BEGINHMM>" => Ok(BeginHmm),
+        "<ENDHMM>" => Ok(EndHmm),
         "<NUMSTATES>" => {
-            let number: u32 = values[1].parse().expect("Num State");
-            *lines = &lines[1..];
-            NumStates(number)
+            let number: u32 = parse_value(lines, values.get(1), "number of states")?;
+            if number < 3 {
+                return Err(lines.error(values[1], "at least 3 states"));
+            }
+            Ok(NumStates(number))
         },
         "<TRANSP>" => {
-            let n: usize = values[1].parse().expect("TP Size");
+            let n: usize = parse_value(lines, values.get(1), "transition matrix size")?;
             let mut tp: Vec<Vec<f64>> = Vec::new();
-            for i in 1..=n {
-                tp.push(get_float_values(&lines[i]));
+            for _ in 0..n {
+                let line = lines.next("transition matrix row")?;
+                let row = get_float_values(lines, line)?;
+                if row.len() != n {
+                    return Err(lines.error(&row.len().to_string(), &format!("{} transition probabilities", n)));
+                }
+                tp.push(row);
             }
-            *lines = &lines[n + 1..];
-            TransProb(tp)
+            Ok(TransProb(tp))
         },
-        _ => panic!("Unexpected Input: {:?}", lines[0]),
+        token => Err(lines.error(token, "~h, <BEGINHMM>, <NUMSTATES>, <TRANSP> or <ENDHMM>")),
     }
 }
 
-pub fn read_phone(lines: &mut &[String], index: usize) -> Phone {
+fn read_phone(lines: &mut Lines, index: usize) -> Result<Phone> {
     let mut name: String = String::new();
     let mut n_states: usize = 0;
 
     // read phone name, <NUMSTATES>, and <BEGINHMM>
     for _ in 0..3 {
-        match read_one_input(lines) {
+        match read_one_input(lines)? {
             PhoneName(_name) => name = _name,
             NumStates(num) => n_states = (num - 2) as usize,
-            _ => ()
+            BeginHmm => (),
+            _ => return Err(lines.unexpected("~h, <BEGINHMM> or <NUMSTATES>")),
         }
     }
 
     // read transition probability matrix
-    let mut in_prob = Vec::new();
-    let mut trans_prob = Vec::new();
-    let mut out_prob = Vec::new();
-    let mut skip_prob = 0f64;
-    match read_one_input(lines) {
-        TransProb(_tp) => {
-            in_prob = _tp[0][1..=n_states].to_vec();
-
-            for i in 1..=n_states {
-                trans_prob.push(_tp[i][1..=n_states].to_vec());
-            }
-
-            for i in 1..=n_states {
-                out_prob.push(_tp[i][n_states + 1]);
-            }
+    let tp = match read_one_input(lines)? {
+        TransProb(tp) => tp,
+        _ => return Err(lines.unexpected("<TRANSP>")),
+    };
+    if tp.len() != n_states + 2 {
+        return Err(lines.error(&tp.len().to_string(), &format!("<TRANSP> of size {}", n_states + 2)));
+    }
 
-            skip_prob = _tp[0][n_states + 1];
-        },
-        _ => ()
+    let in_prob = tp[0][1..=n_states].to_vec();
+    let trans_prob = tp[1..=n_states].iter()
+        .map(|row| row[1..=n_states].to_vec())
+        .collect();
+    let out_prob = tp[1..=n_states].iter()
+        .map(|row| row[n_states + 1])
+        .collect();
+    let skip_prob = tp[0][n_states + 1];
+
+    match read_one_input(lines)? {
+        EndHmm => (),
+        _ => return Err(lines.unexpected("<ENDHMM>")),
     }
-    
-    read_one_input(lines); // read <ENDHMM>
 
-    Phone {
+    Ok(Phone {
         index,
         name, n_states,
         in_prob, trans_prob, out_prob, skip_prob
-    }
+    })
 }
 
-pub fn read_phones(hmm_file_path: &str) -> Vec<Phone> {
-    use std::ffi::OsStr;
-    let all_lines = fileutil::read_lines(OsStr::new(hmm_file_path));
+pub fn read_phones(hmm_file_path: &str) -> Result<Vec<Phone>> {
+    let all_lines = fileutil::read_lines(OsStr::new(hmm_file_path))?;
 
     let mut phones: Vec<Phone> = Vec::new();
 
-    let mut remaining_lines = &all_lines[..];
+    let mut remaining_lines = Lines { path: hmm_file_path, lines: &all_lines, position: 0 };
 
     let mut index = 0;
-    while remaining_lines.len() > 0 {
-        phones.push(read_phone(&mut remaining_lines, index));
-        index = index + 1;
+    while !remaining_lines.is_empty() {
+        phones.push(read_phone(&mut remaining_lines, index)?);
+        index += 1;
     }
 
-    phones
-}
\ No newline at end of file
+    Ok(phones)
+}
@@ -0,0 +1,199 @@
+use constants::*;
+use word::Word;
+use dnn::{Dnn, LabelMap, Posteriors};
+use error::{Error, Result};
+use params::Params;
+use network::{self, Network};
+use viterbi::{self, Alignment, AlignmentError, FrameStats, Hypothesis, Lattice, Outputs, Pruning, Search, Transitions};
+
+pub struct Recognizer<'m> {
+    words: &'m [Word<'m>],
+    network: Network,
+    params: Params,
+    transitions: Transitions,
+    pruning: Pruning,
+    outputs: Outputs,
+    label_map: LabelMap,
+    dnn: Dnn,
+}
+
+#[derive(Debug)]
+pub struct Recognition<'w> {
+    pub hypotheses: Vec<Hypothesis<'w>>, // distinct word sequences the network lets end, best first
+    pub lattice: Option<Lattice>, // word indices refer to the recognizer's words
+    pub frame_stats: Vec<FrameStats>, // empty unless kept
+}
+
+impl<'w> Recognition<'w> {
+    // none when pruning left no path the word network lets end
+    pub fn best(&self) -> Option<&Hypothesis<'w>> {
+        self.hypotheses.first()
+    }
+
+    // recognized word names of the best hypothesis without sentence boundaries, empty without one
+    pub fn transcript(&self) -> Vec<&'w str> {
+        self.best().map(|best| best.transcript()).unwrap_or_default()
+    }
+
+    // mean number of states expanded per frame
+    pub fn mean_expanded(&self) -> f64 {
+        let n_expanded: usize = self.frame_stats.iter().map(|stats| stats.n_expanded).sum();
+        n_expanded as f64 / self.frame_stats.len().max(1) as f64
+    }
+}
+
+// words decoded so far in a session: the stable ones, which no later frame can change,
+// then the best guess at the words after them, without the word the best path is in
+#[derive(Clone, Debug)]
+pub struct PartialResult<'w> {
+    pub stable: Vec<&'w str>,
+    pub tentative: Vec<&'w str>,
+}
+
+// decodes an utterance whose frames arrive in chunks.
+// a frame is decoded once the frames its spectrum window looks ahead to have arrived,
+// and the spectrogram is kept only from the earliest frame a later window looks back to.
+pub struct Session<'r, 'm: 'r> {
+    recognizer: &'r mut Recognizer<'m>,
+    spectrogram: Vec<[f64; N_DIMENSION]>, // frames from offset on
+    offset: usize,
+    n_frames: usize, // frames pushed
+    search: Search,
+}
+
+impl<'r, 'm> Session<'r, 'm> {
+    pub fn push_frames(&mut self, frames: &[[f64; N_DIMENSION]]) -> Result<()> {
+        self.spectrogram.extend_from_slice(frames);
+        self.n_frames += frames.len();
+        let n_ready = self.n_frames.saturating_sub(self.recognizer.dnn.right_context());
+        self.decode_until(n_ready)
+    }
+
+    pub fn partial_result(&self) -> PartialResult<'m> {
+        let words = self.recognizer.words;
+        let names = |segments: &[viterbi::Segment]| -> Vec<&'m str> {
+            segments.iter()
+                .map(|segment| &words[segment.word])
+                .filter(|word| !word.is_sentence_boundary())
+                .map(|word| word.name.as_str())
+                .collect()
+        };
+
+        let n_stable = self.search.committed().len();
+        let tentative = match self.search.best_partial_path() {
+            Some(path) => names(&path.segments[n_stable..]),
+            None => Vec::new(),
+        };
+        PartialResult { stable: names(self.search.committed()), tentative }
+    }
+
+    // decodes the frames left, taking the last frame pushed as the end of the utterance
+    pub fn finalize(mut self) -> Result<Recognition<'m>> {
+        if self.n_frames == 0 {
+            return Err(Error::NoFrames { path: "session".to_string() });
+        }
+        let n_frames = self.n_frames;
+        self.decode_until(n_frames)?;
+
+        let output = viterbi::finish_search(self.search, self.recognizer.words, &self.recognizer.outputs);
+        Ok(Recognition { hypotheses: output.hypotheses, lattice: output.lattice, frame_stats: output.frame_stats })
+    }
+
+    fn decode_until(&mut self, end: usize) -> Result<()> {
+        let start = self.search.n_frames();
+        if end <= start {
+            return Ok(());
+        }
+
+        let posteriors = self.recognizer.dnn.compute_frame_posteriors(&self.spectrogram, self.offset, start..end)?;
+        for t in 0..posteriors.n_frames() {
+            self.search.step(posteriors.frame(t));
+        }
+
+        let keep_from = (end as i32 + self.recognizer.dnn.spectrum_window_range.0).max(0) as usize;
+        if keep_from > self.offset {
+            self.spectrogram.drain(..keep_from - self.offset);
+            self.offset = keep_from;
+        }
+        Ok(())
+    }
+}
+
+impl<'m> Recognizer<'m> {
+    pub fn new(words: &'m [Word<'m>], dnn: Dnn) -> Recognizer<'m> {
+        let params = Params::default();
+        let network = network::bigram(words);
+        let transitions = viterbi::wire_transitions(words, &network, &params);
+        let label_map = dnn.label_map(&params);
+        Recognizer { words, network, params, transitions, pruning: Pruning::default(), outputs: Outputs::default(), label_map, dnn }
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    // rewires the transitions when the language model weights change
+    pub fn set_params(&mut self, params: Params) {
+        if params.lm_scale != self.params.lm_scale || params.word_penalty != self.params.word_penalty {
+            self.transitions = viterbi::wire_transitions(self.words, &self.network, &params);
+        }
+        self.label_map = self.dnn.label_map(&params);
+        self.params = params;
+    }
+
+    // decodes over a word network instead of the bigram loop of the words
+    pub fn set_network(&mut self, network: Network) {
+        self.transitions = viterbi::wire_transitions(self.words, &network, &self.params);
+        self.network = network;
+    }
+
+    pub fn set_pruning(&mut self, pruning: Pruning) {
+        self.pruning = pruning;
+    }
+
+    // number of hypotheses to keep, each a distinct word sequence
+    pub fn set_n_best(&mut self, n_best: usize) {
+        self.outputs.n_best = n_best.max(1);
+    }
+
+    pub fn set_keep_lattice(&mut self, keep_lattice: bool) {
+        self.outputs.keep_lattice = keep_lattice;
+    }
+
+    // records the active and expanded states of every frame, e.g. to report search speed
+    pub fn set_keep_frame_stats(&mut self, keep_frame_stats: bool) {
+        self.outputs.keep_frame_stats = keep_frame_stats;
+    }
+
+    // gives every hypothesis word a confidence from the posteriors of a lattice
+    // whose log probs are multiplied by the scale, or none when None
+    pub fn set_confidence_scale(&mut self, confidence_scale: Option<f64>) {
+        self.outputs.confidence_scale = confidence_scale;
+    }
+
+    pub fn decode(&mut self, spectrogram: &[[f64; N_DIMENSION]]) -> Result<Recognition<'m>> {
+        let posteriors = self.compute_posteriors(spectrogram)?;
+        Ok(self.decode_posteriors(&posteriors))
+    }
+
+    pub fn compute_posteriors(&mut self, spectrogram: &[[f64; N_DIMENSION]]) -> Result<Posteriors> {
+        self.dnn.compute_posteriors(spectrogram)
+    }
+
+    // starts decoding an utterance frame by frame, with the current settings
+    pub fn session<'r>(&'r mut self) -> Session<'r, 'm> {
+        let search = viterbi::new_search(&self.label_map, self.words, &self.transitions, &self.pruning, &self.outputs);
+        Session { recognizer: self, spectrogram: Vec::new(), offset: 0, n_frames: 0, search }
+    }
+
+    // decodes dnn posteriors computed earlier, skipping the network
+    pub fn decode_posteriors(&self, posteriors: &Posteriors) -> Recognition<'m> {
+        let output = viterbi::run(posteriors, &self.label_map, self.words, &self.transitions, &self.pruning, &self.outputs);
+        Recognition { hypotheses: output.hypotheses, lattice: output.lattice, frame_stats: output.frame_stats }
+    }
+
+    // forced alignment of dnn posteriors to the words of a known transcript
+    pub fn align(&self, posteriors: &Posteriors, transcript: &[&str]) -> ::std::result::Result<Alignment, AlignmentError> {
+        viterbi::align(posteriors, &self.label_map, self.words, transcript)
+    }
+}
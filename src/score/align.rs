@@ -0,0 +1,133 @@
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    Correct(String),
+    Substitution { reference: String, recognized: String },
+    Deletion(String),
+    Insertion(String),
+}
+
+use self::Edit::*;
+
+// aligns recognized words to reference words with minimum Levenshtein distance
+// and returns the edit sequence in utterance order.
+pub fn align(reference: &[String], recognized: &[String]) -> Vec<Edit> {
+    let n_ref = reference.len();
+    let n_rec = recognized.len();
+
+    // distance[i][j]: edit distance between reference[..i] and recognized[..j]
+    let mut distance = vec![vec![0usize; n_rec + 1]; n_ref + 1];
+    for (i, row) in distance.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, value) in distance[0].iter_mut().enumerate() {
+        *value = j;
+    }
+
+    for i in 1..=n_ref {
+        for j in 1..=n_rec {
+            let diagonal = distance[i - 1][j - 1] + if reference[i - 1] == recognized[j - 1] { 0 } else { 1 };
+            let deletion = distance[i - 1][j] + 1;
+            let insertion = distance[i][j - 1] + 1;
+            distance[i][j] = diagonal.min(deletion).min(insertion);
+        }
+    }
+
+    // backtrace, preferring matches and substitutions over deletions and insertions
+    let mut edits = Vec::with_capacity(n_ref.max(n_rec));
+    let (mut i, mut j) = (n_ref, n_rec);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let is_same = reference[i - 1] == recognized[j - 1];
+            let cost = if is_same { 0 } else { 1 };
+            if distance[i][j] == distance[i - 1][j - 1] + cost {
+                edits.push(
+                    if is_same {
+                        Correct(reference[i - 1].clone())
+                    } else {
+                        Substitution {
+                            reference: reference[i - 1].clone(),
+                            recognized: recognized[j - 1].clone(),
+                        }
+                    }
+                );
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+
+        if i > 0 && distance[i][j] == distance[i - 1][j] + 1 {
+            edits.push(Deletion(reference[i - 1].clone()));
+            i -= 1;
+        } else {
+            edits.push(Insertion(recognized[j - 1].clone()));
+            j -= 1;
+        }
+    }
+
+    edits.reverse();
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    fn correct(word: &str) -> Edit {
+        Correct(word.to_string())
+    }
+
+    #[test]
+    fn same_words_are_correct() {
+        assert_eq!(align(&words("one two"), &words("one two")), vec![correct("one"), correct("two")]);
+    }
+
+    #[test]
+    fn substitution() {
+        assert_eq!(align(&words("one two three"), &words("one five three")), vec![
+            correct("one"),
+            Substitution { reference: "two".to_string(), recognized: "five".to_string() },
+            correct("three"),
+        ]);
+    }
+
+    #[test]
+    fn deletion() {
+        assert_eq!(align(&words("one two three"), &words("one three")), vec![
+            correct("one"),
+            Deletion("two".to_string()),
+            correct("three"),
+        ]);
+    }
+
+    #[test]
+    fn insertion() {
+        assert_eq!(align(&words("one three"), &words("one two three")), vec![
+            correct("one"),
+            Insertion("two".to_string()),
+            correct("three"),
+        ]);
+    }
+
+    #[test]
+    fn substitution_is_preferred_to_deletion_and_insertion() {
+        assert_eq!(align(&words("one"), &words("two")), vec![
+            Substitution { reference: "one".to_string(), recognized: "two".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn empty_hypothesis_deletes_every_word() {
+        assert_eq!(align(&words("one two"), &[]), vec![Deletion("one".to_string()), Deletion("two".to_string())]);
+    }
+
+    #[test]
+    fn empty_reference_inserts_every_word() {
+        assert_eq!(align(&[], &words("one two")), vec![Insertion("one".to_string()), Insertion("two".to_string())]);
+        assert!(align(&[], &[]).is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use std::ffi::OsStr;
+use std::fmt::{self, Write};
+
+use error::Result;
+use fileutil;
+use score::{percent, Edit, Scores};
+
+// confusion counts between reference words (rows) and recognized words (columns).
+#[derive(Debug)]
+pub struct Confusion {
+    pub vocabulary: Vec<String>,
+    pub matrix: Vec<Vec<usize>>, // matrix[reference][recognized]
+    pub deletions: Vec<usize>,
+    pub insertions: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WordStats {
+    pub n_reference: usize,
+    pub hits: usize,
+    pub substitutions: usize, // reference word recognized as another word
+    pub deletions: usize,
+    pub insertions: usize,
+    pub false_alarms: usize, // another reference word recognized as this word
+}
+
+impl WordStats {
+    pub fn correct_rate(&self) -> f64 {
+        percent(self.hits, self.n_reference)
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        percent(self.substitutions + self.deletions + self.insertions, self.n_reference)
+    }
+}
+
+pub fn read_vocabulary(path: &str) -> Result<Vec<String>> {
+    let vocabulary = fileutil::read_lines(OsStr::new(path))?.into_iter()
+        .map(|line| line.trim().to_string())
+        .filter(|word| !word.is_empty())
+        .collect();
+    Ok(vocabulary)
+}
+
+impl Confusion {
+    // words missing in the vocabulary are appended in order of appearance.
+    pub fn of(vocabulary: &[String], scores: &Scores) -> Confusion {
+        let mut confusion = Confusion {
+            vocabulary: Vec::new(),
+            matrix: Vec::new(),
+            deletions: Vec::new(),
+            insertions: Vec::new(),
+        };
+        for word in vocabulary.iter() {
+            confusion.index_of(word);
+        }
+
+        for utterance in scores.utterances.iter() {
+            for edit in utterance.edits.iter() {
+                match edit {
+                    Edit::Correct(word) => {
+                        let i = confusion.index_of(word);
+                        confusion.matrix[i][i] += 1;
+                    },
+                    Edit::Substitution { reference, recognized } => {
+                        let i = confusion.index_of(reference);
+                        let j = confusion.index_of(recognized);
+                        confusion.matrix[i][j] += 1;
+                    },
+                    Edit::Deletion(word) => {
+                        let i = confusion.index_of(word);
+                        confusion.deletions[i] += 1;
+                    },
+                    Edit::Insertion(word) => {
+                        let j = confusion.index_of(word);
+                        confusion.insertions[j] += 1;
+                    },
+                }
+            }
+        }
+
+        confusion
+    }
+
+    pub fn word_stats(&self) -> Vec<WordStats> {
+        let n = self.vocabulary.len();
+        (0..n).map(|i| {
+            let hits = self.matrix[i][i];
+            let n_recognized_as: usize = (0..n).map(|j| self.matrix[i][j]).sum();
+            let n_false_alarms: usize = (0..n).map(|j| self.matrix[j][i]).sum::<usize>() - hits;
+            WordStats {
+                n_reference: n_recognized_as + self.deletions[i],
+                hits,
+                substitutions: n_recognized_as - hits,
+                deletions: self.deletions[i],
+                insertions: self.insertions[i],
+                false_alarms: n_false_alarms,
+            }
+        }).collect()
+    }
+
+    pub fn matrix_csv(&self) -> String {
+        let mut csv = String::new();
+        let _ = write!(csv, "reference/recognized");
+        for word in self.vocabulary.iter() {
+            let _ = write!(csv, ",{}", word);
+        }
+        let _ = writeln!(csv, ",Del");
+
+        for (i, word) in self.vocabulary.iter().enumerate() {
+            let _ = write!(csv, "{}", word);
+            for count in self.matrix[i].iter() {
+                let _ = write!(csv, ",{}", count);
+            }
+            let _ = writeln!(csv, ",{}", self.deletions[i]);
+        }
+
+        let _ = write!(csv, "Ins");
+        for count in self.insertions.iter() {
+            let _ = write!(csv, ",{}", count);
+        }
+        let _ = writeln!(csv, ",");
+        csv
+    }
+
+    pub fn word_stats_csv(&self) -> String {
+        let mut csv = String::from("word,reference,hits,substitutions,deletions,insertions,false_alarms,correct_rate,error_rate\n");
+        for (word, stats) in self.vocabulary.iter().zip(self.word_stats()) {
+            let _ = writeln!(csv, "{},{},{},{},{},{},{},{:.2},{:.2}",
+                word, stats.n_reference, stats.hits, stats.substitutions, stats.deletions,
+                stats.insertions, stats.false_alarms, stats.correct_rate(), stats.error_rate());
+        }
+        csv
+    }
+
+    fn index_of(&mut self, word: &str) -> usize {
+        if let Some(index) = self.vocabulary.iter().position(|w| w == word) {
+            return index;
+        }
+
+        self.vocabulary.push(word.to_string());
+        for row in self.matrix.iter_mut() {
+            row.push(0);
+        }
+        self.matrix.push(vec![0; self.vocabulary.len()]);
+        self.deletions.push(0);
+        self.insertions.push(0);
+        self.vocabulary.len() - 1
+    }
+}
+
+impl fmt::Display for Confusion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.vocabulary.iter().map(|word| word.len()).max().unwrap_or(0).max(4) + 1;
+
+        writeln!(f, "--------------------- Confusion Matrix --------------------")?;
+        write!(f, "{:>w$}", "", w = width)?;
+        for word in self.vocabulary.iter() {
+            write!(f, "{:>w$}", word, w = width)?;
+        }
+        writeln!(f, "{:>w$}", "Del", w = width)?;
+
+        for (i, word) in self.vocabulary.iter().enumerate() {
+            write!(f, "{:>w$}", word, w = width)?;
+            for count in self.matrix[i].iter() {
+                write!(f, "{:>w$}", count, w = width)?;
+            }
+            writeln!(f, "{:>w$}", self.deletions[i], w = width)?;
+        }
+
+        write!(f, "{:>w$}", "Ins", w = width)?;
+        for count in self.insertions.iter() {
+            write!(f, "{:>w$}", count, w = width)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "------------------------ Per Word -------------------------")?;
+        writeln!(f, "{:>w$} {:>6} {:>6} {:>6} {:>6} {:>6} {:>6} {:>7} {:>7}",
+            "", "N", "H", "S", "D", "I", "FA", "%Corr", "%Err", w = width)?;
+        for (word, stats) in self.vocabulary.iter().zip(self.word_stats()) {
+            writeln!(f, "{:>w$} {:>6} {:>6} {:>6} {:>6} {:>6} {:>6} {:>7.2} {:>7.2}",
+                word, stats.n_reference, stats.hits, stats.substitutions, stats.deletions,
+                stats.insertions, stats.false_alarms, stats.correct_rate(), stats.error_rate(), w = width)?;
+        }
+        Ok(())
+    }
+}
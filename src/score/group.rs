@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use score::{Scores, Summary, UtteranceScore};
+
+#[derive(Debug)]
+pub struct Group {
+    pub name: String,
+    pub summary: Summary,
+}
+
+// error summaries grouped by the tst/<gender>/<speaker>/<utterance> layout.
+#[derive(Debug)]
+pub struct Breakdown {
+    pub genders: Vec<Group>,
+    pub speakers: Vec<Group>,
+}
+
+impl Breakdown {
+    pub fn of(scores: &Scores) -> Breakdown {
+        Breakdown {
+            genders: group_by(&scores.utterances, |utterance| gender_of(&utterance.key)),
+            speakers: group_by(&scores.utterances, |utterance| speaker_of(&utterance.key)),
+        }
+    }
+
+    // speakers sorted by word error rate, worst first
+    pub fn worst_speakers(&self, n: usize) -> Vec<&Group> {
+        let mut speakers: Vec<&Group> = self.speakers.iter().collect();
+        speakers.sort_by(|a, b| {
+            b.summary.word_error_rate()
+                .partial_cmp(&a.summary.word_error_rate())
+                .expect("Error Rate")
+                .then_with(|| b.summary.sentence_error_rate()
+                    .partial_cmp(&a.summary.sentence_error_rate())
+                    .expect("Error Rate"))
+        });
+        speakers.truncate(n);
+        speakers
+    }
+
+    pub fn display_worst(&self, n_worst_speakers: usize) -> BreakdownDisplay<'_> {
+        BreakdownDisplay { breakdown: self, n_worst_speakers }
+    }
+}
+
+pub struct BreakdownDisplay<'a> {
+    breakdown: &'a Breakdown,
+    n_worst_speakers: usize,
+}
+
+impl<'a> fmt::Display for BreakdownDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "------------------------ By Gender ------------------------")?;
+        for group in self.breakdown.genders.iter() {
+            write_group(f, group)?;
+        }
+
+        writeln!(f, "------------------------ By Speaker -----------------------")?;
+        for group in self.breakdown.speakers.iter() {
+            write_group(f, group)?;
+        }
+
+        writeln!(f, "--------------------- Worst {:>2} Speakers ------------------", self.n_worst_speakers)?;
+        for group in self.breakdown.worst_speakers(self.n_worst_speakers) {
+            write_group(f, group)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_group(f: &mut fmt::Formatter, group: &Group) -> fmt::Result {
+    let summary = &group.summary;
+    writeln!(f, "{:<10} SENT %Error={:>6.2} [N={:>4}]  WORD %Error={:>6.2} [S={}, D={}, I={}, N={}]",
+        group.name,
+        summary.sentence_error_rate(), summary.n_sentences,
+        summary.word_error_rate(),
+        summary.counts.substitutions, summary.counts.deletions, summary.counts.insertions,
+        summary.counts.n_words)
+}
+
+fn group_by<F>(utterances: &[UtteranceScore], name_of: F) -> Vec<Group>
+    where F: Fn(&UtteranceScore) -> String {
+    let mut groups: BTreeMap<String, Vec<&UtteranceScore>> = BTreeMap::new();
+    for utterance in utterances.iter() {
+        groups.entry(name_of(utterance)).or_default().push(utterance);
+    }
+
+    groups.into_iter()
+        .map(|(name, utterances)| Group { name, summary: Summary::of(utterances.into_iter()) })
+        .collect()
+}
+
+// directory names above the utterance, nearest first
+fn parent_names(key: &str) -> Vec<String> {
+    Path::new(key).ancestors()
+        .skip(1)
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect()
+}
+
+fn gender_of(key: &str) -> String {
+    parent_names(key).get(1).cloned().unwrap_or_else(|| String::from("?"))
+}
+
+fn speaker_of(key: &str) -> String {
+    let names = parent_names(key);
+    match (names.get(1), names.first()) {
+        (Some(gender), Some(speaker)) => format!("{}/{}", gender, speaker),
+        (None, Some(speaker)) => speaker.clone(),
+        _ => String::from("?"),
+    }
+}
@@ -0,0 +1,181 @@
+mod align;
+mod confusion;
+mod group;
+
+pub use self::align::{align, Edit};
+pub use self::confusion::{read_vocabulary, Confusion};
+pub use self::group::{Breakdown, Group};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::AddAssign;
+
+use mlf::Transcription;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Counts {
+    pub n_words: usize, // number of reference words
+    pub hits: usize,
+    pub substitutions: usize,
+    pub deletions: usize,
+    pub insertions: usize,
+}
+
+impl Counts {
+    pub fn from_edits(edits: &[Edit]) -> Counts {
+        let mut counts = Counts::default();
+        for edit in edits.iter() {
+            match edit {
+                Edit::Correct(_) => counts.hits += 1,
+                Edit::Substitution { .. } => counts.substitutions += 1,
+                Edit::Deletion(_) => counts.deletions += 1,
+                Edit::Insertion(_) => counts.insertions += 1,
+            }
+        }
+        counts.n_words = counts.hits + counts.substitutions + counts.deletions;
+        counts
+    }
+
+    pub fn n_errors(&self) -> usize {
+        self.substitutions + self.deletions + self.insertions
+    }
+
+    pub fn word_error_rate(&self) -> f64 {
+        percent(self.n_errors(), self.n_words)
+    }
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Counts) {
+        self.n_words += other.n_words;
+        self.hits += other.hits;
+        self.substitutions += other.substitutions;
+        self.deletions += other.deletions;
+        self.insertions += other.insertions;
+    }
+}
+
+#[derive(Debug)]
+pub struct UtteranceScore {
+    pub key: String,
+    pub edits: Vec<Edit>,
+    pub counts: Counts,
+}
+
+impl UtteranceScore {
+    pub fn is_error(&self) -> bool {
+        self.counts.n_errors() > 0
+    }
+}
+
+#[derive(Debug)]
+pub struct Scores {
+    pub utterances: Vec<UtteranceScore>,
+    pub missing: Vec<String>, // recognized utterances without reference
+}
+
+impl Scores {
+    pub fn summary(&self) -> Summary {
+        Summary::of(self.utterances.iter())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Summary {
+    pub n_sentences: usize,
+    pub n_sentence_errors: usize,
+    pub counts: Counts,
+}
+
+impl Summary {
+    pub fn of<'a, I: Iterator<Item = &'a UtteranceScore>>(utterances: I) -> Summary {
+        let mut summary = Summary::default();
+        for utterance in utterances {
+            summary.n_sentences += 1;
+            if utterance.is_error() {
+                summary.n_sentence_errors += 1;
+            }
+            summary.counts += utterance.counts;
+        }
+        summary
+    }
+
+    pub fn word_error_rate(&self) -> f64 {
+        self.counts.word_error_rate()
+    }
+
+    pub fn sentence_error_rate(&self) -> f64 {
+        percent(self.n_sentence_errors, self.n_sentences)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = &self.counts;
+        writeln!(f, "SENT: %Error={:.2} [E={}, N={}]",
+            self.sentence_error_rate(), self.n_sentence_errors, self.n_sentences)?;
+        write!(f, "WORD: %Error={:.2} [H={}, S={}, D={}, I={}, N={}]",
+            self.word_error_rate(), c.hits, c.substitutions, c.deletions, c.insertions, c.n_words)
+    }
+}
+
+// scores every recognized utterance against the reference with the same key.
+pub fn score(reference: &[Transcription], recognized: &[Transcription]) -> Scores {
+    let reference: HashMap<String, &Transcription> = reference.iter()
+        .map(|transcription| (transcription.key(), transcription))
+        .collect();
+
+    let mut utterances = Vec::new();
+    let mut missing = Vec::new();
+    for transcription in recognized.iter() {
+        let key = transcription.key();
+        match reference.get(&key) {
+            Some(reference) => {
+                let edits = align(&reference.words, &transcription.words);
+                let counts = Counts::from_edits(&edits);
+                utterances.push(UtteranceScore { key, edits, counts });
+            },
+            None => missing.push(key),
+        }
+    }
+
+    Scores { utterances, missing }
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0f64
+    } else {
+        count as f64 / total as f64 * 100f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mlf::utterance_key;
+
+    fn transcription(name: &str, text: &str) -> Transcription {
+        Transcription { name: name.to_string(), words: text.split_whitespace().map(String::from).collect() }
+    }
+
+    #[test]
+    fn rec_and_lab_names_have_the_same_key() {
+        assert_eq!(utterance_key("\"tst/f/ak/1237743.rec\""), "tst/f/ak/1237743");
+        assert_eq!(utterance_key("tst/f/ak/1237743.lab"), utterance_key("\"tst/f/ak/1237743.rec\""));
+        assert_eq!(utterance_key("tst/f/ak/1237743"), "tst/f/ak/1237743");
+    }
+
+    #[test]
+    fn recognized_utterances_are_scored_against_the_reference_of_the_same_key() {
+        let reference = vec![transcription("\"tst/f/ak/1237743.lab\"", "one two three"), transcription("\"tst/m/ah/27o6571.lab\"", "two")];
+        let recognized = vec![transcription("tst/f/ak/1237743.rec", "one three"), transcription("tst/f/ak/9999999.rec", "one")];
+
+        let scores = score(&reference, &recognized);
+        assert_eq!(scores.utterances.len(), 1);
+        assert_eq!(scores.utterances[0].key, "tst/f/ak/1237743");
+        let counts = scores.utterances[0].counts;
+        assert_eq!((counts.n_words, counts.hits, counts.substitutions, counts.deletions, counts.insertions), (3, 2, 0, 1, 0));
+        assert_eq!(scores.missing, vec!["tst/f/ak/9999999".to_string()]);
+    }
+}
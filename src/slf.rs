@@ -0,0 +1,30 @@
+use std::io::{self, Write};
+
+use constants::*;
+use word::Word;
+use viterbi::Lattice;
+
+// writes a lattice in HTK Standard Lattice Format.
+// scores are natural logs, already weighted by the decoding parameters, so lmscale is 1.
+// a= holds all but the language model: acoustic scores, HMM transitions and word penalties.
+pub fn write<W: Write>(writer: &mut W, name: &str, lattice: &Lattice, words: &[Word]) -> io::Result<()> {
+    writeln!(writer, "VERSION=1.0")?;
+    writeln!(writer, "UTTERANCE={}", name)?;
+    writeln!(writer, "lmscale=1.0 wdpenalty=0.0")?;
+    writeln!(writer, "N={} L={}", lattice.nodes.len(), lattice.links.len())?;
+
+    for (index, node) in lattice.nodes.iter().enumerate() {
+        writeln!(writer, "I={} t={:.2}", index, seconds(node.frame))?;
+    }
+    for (index, link) in lattice.links.iter().enumerate() {
+        writeln!(writer, "J={} S={} E={} W={} a={:.4} l={:.4}",
+            index, link.start, link.end, words[link.word].name,
+            link.log_prob - link.lm_log_prob, link.lm_log_prob)?;
+    }
+
+    Ok(())
+}
+
+fn seconds(frame: usize) -> f64 {
+    (frame as u64 * FRAME_PERIOD) as f64 / 1e7
+}
@@ -0,0 +1,258 @@
+use std::error;
+use std::fmt;
+
+use word::Word;
+use phone::Phone;
+use dnn::{LabelMap, Posteriors};
+use params::Params;
+use mlf::Label;
+use network;
+use viterbi::{StateRef, wire_transitions};
+use viterbi::graph::Graph;
+
+// a word, phone or state of an alignment and its own log score
+#[derive(Clone, Debug)]
+pub struct Unit {
+    pub name: String,
+    pub start_frame: usize,
+    pub end_frame: usize, // last frame of the unit
+    pub log_prob: f64,
+}
+
+impl Unit {
+    pub fn label(&self) -> Label<'_> {
+        Label::of_frames(&self.name, self.start_frame, self.end_frame, self.log_prob)
+    }
+}
+
+// best path through the words of a transcript, segmented at every level.
+// states are named "phone[n]" with n counted from 1, as in label_info.txt of the dnn.
+#[derive(Clone, Debug)]
+pub struct Alignment {
+    pub words: Vec<Unit>, // with the silences taken
+    pub phones: Vec<Unit>,
+    pub states: Vec<Unit>,
+    pub log_prob: f64,
+}
+
+#[derive(Debug)]
+pub enum AlignmentError {
+    UnknownWord(String),
+    NoPath, // the frames can't pass through every word
+}
+
+impl fmt::Display for AlignmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlignmentError::UnknownWord(name) => write!(f, "word {:?} not in the dictionary", name),
+            AlignmentError::NoPath => write!(f, "no path through the transcript"),
+        }
+    }
+}
+
+impl error::Error for AlignmentError {}
+
+// pronunciations of a transcript word, or of a silence which may be skipped
+struct Group {
+    words: Vec<usize>,
+    is_optional: bool,
+}
+
+// the transcript as a chain of words, every pronunciation a word of its own
+struct LinearWords<'p> {
+    words: Vec<Word<'p>>,
+    exits: Vec<(StateRef, f64)>, // states the utterance may end in and their exit log probs
+}
+
+// optional silence, as pronounced by the sentence boundary words, goes at both ends.
+// every other pronunciation ends with an "sp", which wire() lets the path skip.
+fn linear_words<'p>(words: &[Word<'p>], transcript: &[&str]) -> Result<LinearWords<'p>, AlignmentError> {
+    let silences: Vec<&Word<'p>> = words.iter().filter(|word| word.is_sentence_boundary()).collect();
+    let sp = words.iter()
+        .flat_map(|word| word.phones.iter())
+        .find(|phone| phone.name == "sp")
+        .cloned();
+
+    let mut linear: Vec<Word<'p>> = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut push_group = |pronunciations: &[&Word<'p>], is_optional: bool| {
+        let mut group = Group { words: Vec::new(), is_optional };
+        for pronunciation in pronunciations.iter() {
+            let mut phones = pronunciation.phones.clone();
+            if let Some(sp) = sp {
+                if !pronunciation.is_sentence_boundary() && phones.last().map(|phone| phone.name.as_str()) != Some("sp") {
+                    phones.push(sp);
+                }
+            }
+            group.words.push(linear.len());
+            linear.push(Word { name: pronunciation.name.clone(), phones, head_prob: 0f64, next_word_prob: Vec::new() });
+        }
+        groups.push(group);
+    };
+
+    push_group(&silences, true);
+    for &name in transcript.iter() {
+        let pronunciations: Vec<&Word<'p>> = words.iter().filter(|word| word.name == name).collect();
+        if pronunciations.is_empty() {
+            return Err(AlignmentError::UnknownWord(name.to_string()));
+        }
+        push_group(&pronunciations, false);
+    }
+    push_group(&silences, true);
+
+    let n_words = linear.len();
+    for word in linear.iter_mut() {
+        word.next_word_prob.resize(n_words, 0f64);
+    }
+    for &w in followers(&groups, 0).iter() {
+        linear[w].head_prob = 1f64;
+    }
+    for (g, group) in groups.iter().enumerate() {
+        for &next_w in followers(&groups, g + 1).iter() {
+            for &w in group.words.iter() {
+                linear[w].next_word_prob[next_w] = 1f64;
+            }
+        }
+    }
+
+    let mut exits = Vec::new();
+    for (g, group) in groups.iter().enumerate() {
+        if groups[g + 1..].iter().all(|later| later.is_optional) {
+            for &w in group.words.iter() {
+                exits.extend(exit_log_probs(w, &linear[w].phones));
+            }
+        }
+    }
+
+    Ok(LinearWords { words: linear, exits })
+}
+
+// words of the groups from the given one up to the first which can't be skipped
+fn followers(groups: &[Group], from: usize) -> Vec<usize> {
+    let mut followers = Vec::new();
+    for group in groups[from..].iter() {
+        followers.extend(group.words.iter().cloned());
+        if !group.is_optional {
+            break;
+        }
+    }
+    followers
+}
+
+// log probs of leaving a word from each of its states, the way wire() leaves for a next word
+fn exit_log_probs(w: usize, phones: &[&Phone]) -> Vec<(StateRef, f64)> {
+    let mut exits = Vec::new();
+    let p = phones.len() - 1;
+    let phone = phones[p];
+    for s in 0..phone.n_states {
+        if phone.out_prob[s] > 0f64 {
+            exits.push((StateRef { node: w, phone: p, state: s }, phone.out_prob[s].ln()));
+        }
+    }
+
+    if phone.name == "sp" && p > 0 {
+        let prev_phone = phones[p - 1];
+        for s in 0..prev_phone.n_states {
+            let prob = prev_phone.out_prob[s] * phone.skip_prob;
+            if prob > 0f64 {
+                exits.push((StateRef { node: w, phone: p - 1, state: s }, prob.ln()));
+            }
+        }
+    }
+    exits
+}
+
+// forced alignment: Viterbi search over the words of a known transcript, keeping the best
+// source of every state at every frame so that the path can be traced back state by state.
+// unlike the token passing search, memory grows with frames times states of the transcript,
+// so this is meant for single utterances and not for long recordings.
+pub fn align(posteriors: &Posteriors, label_map: &LabelMap, words: &[Word], transcript: &[&str]) -> Result<Alignment, AlignmentError> {
+    let linear = linear_words(words, transcript)?;
+    // the transcript fixes the words, so there is no language model score or word penalty
+    let transitions = wire_transitions(&linear.words, &network::bigram(&linear.words), &Params { word_penalty: 0f64, ..Params::default() });
+    let graph = Graph::new(&linear.words, &transitions, label_map);
+
+    let n_frames = posteriors.n_frames();
+    if n_frames == 0 {
+        return Err(AlignmentError::NoPath);
+    }
+
+    // best log prob of each state at each frame and the state it came from
+    let mut log_probs = vec![vec![f64::NEG_INFINITY; graph.n_states()]; n_frames];
+    let mut sources: Vec<Vec<Option<usize>>> = vec![vec![None; graph.n_states()]; n_frames];
+    for arc in graph.from_start.iter() {
+        let log_prob = arc.log_prob + graph.observation_log_prob(posteriors.frame(0), arc.dest);
+        if log_prob > log_probs[0][arc.dest] {
+            log_probs[0][arc.dest] = log_prob;
+        }
+    }
+    for t in 1..n_frames {
+        let frame = posteriors.frame(t);
+        let (done, rest) = log_probs.split_at_mut(t);
+        let (prev, current) = (&done[t - 1], &mut rest[0]);
+        for (source, &source_log_prob) in prev.iter().enumerate() {
+            if source_log_prob == f64::NEG_INFINITY {
+                continue;
+            }
+            for arc in graph.from_state[source].iter() {
+                let log_prob = source_log_prob + arc.log_prob + graph.observation_log_prob(frame, arc.dest);
+                if log_prob > current[arc.dest] {
+                    current[arc.dest] = log_prob;
+                    sources[t][arc.dest] = Some(source);
+                }
+            }
+        }
+    }
+
+    let last_frame = n_frames - 1;
+    let mut best: Option<(usize, f64)> = None;
+    for &(state, exit_log_prob) in linear.exits.iter() {
+        let state = graph.state_number(&state);
+        let log_prob = log_probs[last_frame][state] + exit_log_prob;
+        if log_prob > best.map(|(_, best_log_prob)| best_log_prob).unwrap_or(f64::NEG_INFINITY) {
+            best = Some((state, log_prob));
+        }
+    }
+    let (mut state, log_prob) = best.ok_or(AlignmentError::NoPath)?;
+
+    let mut path = vec![state; n_frames];
+    for t in (1..n_frames).rev() {
+        state = sources[t][state].expect("Source State");
+        path[t - 1] = state;
+    }
+    // scores of the path up to each frame, ending with the exit
+    let mut scores: Vec<f64> = path.iter().enumerate().map(|(t, &state)| log_probs[t][state]).collect();
+    scores[last_frame] = log_prob;
+
+    let state_ref = |state: usize| graph.states[state];
+    let phone_name = |state_number: usize| {
+        let state = state_ref(state_number);
+        &linear.words[graph.word(state_number)].phones[state.phone].name
+    };
+    Ok(Alignment {
+        words: units(&path, &scores, |state| state_ref(state).node, |state| linear.words[graph.word(state)].name.clone()),
+        phones: units(&path, &scores, |state| (state_ref(state).node, state_ref(state).phone), |state| phone_name(state).clone()),
+        states: units(&path, &scores, |state| state, |state| format!("{}[{}]", phone_name(state), state_ref(state).state + 1)),
+        log_prob,
+    })
+}
+
+// runs of frames whose states share a key, scored by the difference of the path scores
+fn units<K, F, N>(path: &[usize], scores: &[f64], key: F, name: N) -> Vec<Unit>
+    where K: PartialEq, F: Fn(usize) -> K, N: Fn(usize) -> String
+{
+    let mut units = Vec::new();
+    let mut start_frame = 0;
+    for (t, &state) in path.iter().enumerate() {
+        let ends_unit = match path.get(t + 1) {
+            Some(&next) => key(next) != key(state),
+            None => true,
+        };
+        if ends_unit {
+            let start_log_prob = if start_frame == 0 { 0f64 } else { scores[start_frame - 1] };
+            units.push(Unit { name: name(state), start_frame, end_frame: t, log_prob: scores[t] - start_log_prob });
+            start_frame = t + 1;
+        }
+    }
+    units
+}
@@ -0,0 +1,116 @@
+use word::Word;
+use dnn::LabelMap;
+use viterbi::{StateRef, Transition, Transitions};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Arc {
+    pub dest: usize,
+    pub log_prob: f64,
+    pub lm_log_prob: f64,
+    pub to_next_word: bool,
+}
+
+// transitions between states numbered in (node, phone, state) order
+pub struct Graph {
+    labels: Vec<Option<usize>>, // posterior column of each state
+    log_offsets: Vec<f64>, // added to the log posterior of each label
+    acoustic_scale: f64,
+    offsets: Vec<Vec<usize>>, // number of the first state of each [node][phone]
+    pub states: Vec<StateRef>, // node, phone and state of each state number
+    node_words: Vec<usize>, // word of each node
+    final_nodes: Vec<bool>, // whether the utterance may end in each node
+    history_words: Vec<Option<usize>>, // by word, same for words of the same name, None for sentence boundaries
+    pub from_start: Vec<Arc>,
+    pub from_state: Vec<Vec<Arc>>,
+}
+
+impl Graph {
+    pub fn new(words: &[Word], transitions: &Transitions, label_map: &LabelMap) -> Graph {
+        let mut offsets: Vec<Vec<usize>> = Vec::with_capacity(transitions.words.len());
+        let mut labels = Vec::new();
+        let mut states = Vec::new();
+        for (n, &w) in transitions.words.iter().enumerate() {
+            let word = &words[w];
+            offsets.push(Vec::with_capacity(word.phones.len()));
+            for (p, phone) in word.phones.iter().enumerate() {
+                offsets[n].push(labels.len());
+                for (s, &label) in label_map.state_labels[phone.index].iter().enumerate() {
+                    labels.push(label);
+                    states.push(StateRef { node: n, phone: p, state: s });
+                }
+            }
+        }
+
+        let to_arc = |t: &Transition| Arc {
+            dest: offsets[t.dest.node][t.dest.phone] + t.dest.state,
+            log_prob: t.log_prob,
+            lm_log_prob: t.lm_log_prob,
+            to_next_word: t.to_next_word,
+        };
+
+        let from_start = transitions.from_start.iter().map(to_arc).collect();
+
+        let mut from_state = Vec::with_capacity(labels.len());
+        for word_transitions in transitions.from_state.iter() {
+            for phone_transitions in word_transitions.iter() {
+                for state_transitions in phone_transitions.iter() {
+                    from_state.push(state_transitions.iter().map(to_arc).collect());
+                }
+            }
+        }
+
+        let history_words = words.iter()
+            .map(|word| if word.is_sentence_boundary() {
+                None
+            } else {
+                words.iter().position(|other| other.name == word.name)
+            })
+            .collect();
+
+        Graph {
+            labels,
+            log_offsets: label_map.log_offsets.clone(),
+            acoustic_scale: label_map.acoustic_scale,
+            offsets,
+            states,
+            node_words: transitions.words.clone(),
+            final_nodes: transitions.is_final.clone(),
+            history_words,
+            from_start,
+            from_state,
+        }
+    }
+
+    pub fn n_states(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn state_number(&self, state: &StateRef) -> usize {
+        self.offsets[state.node][state.phone] + state.state
+    }
+
+    pub fn node(&self, state: usize) -> usize {
+        self.states[state].node
+    }
+
+    pub fn word(&self, state: usize) -> usize {
+        self.node_words[self.states[state].node]
+    }
+
+    // whether the utterance may end in the node of the state
+    pub fn is_final(&self, state: usize) -> bool {
+        self.final_nodes[self.states[state].node]
+    }
+
+    pub fn observation_log_prob(&self, posteriors: &[f32], state: usize) -> f64 {
+        match self.labels[state] {
+            Some(label) => self.acoustic_scale * ((posteriors[label] as f64).ln() + self.log_offsets[label]),
+            None => f64::NEG_INFINITY,
+        }
+    }
+
+    // the word as it counts in word histories: the first word of the same name, or none for sentence boundaries
+    pub fn history_word(&self, word: usize) -> Option<usize> {
+        self.history_words[word]
+    }
+}
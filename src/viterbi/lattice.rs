@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+// a word boundary in time
+#[derive(Clone, Copy, Debug)]
+pub struct Node {
+    pub frame: usize, // frames before the boundary
+}
+
+// a word between two boundaries and its scores, which add up along a path to the path's scores
+#[derive(Clone, Copy, Debug)]
+pub struct Link {
+    pub start: usize, // node index
+    pub end: usize,
+    pub word: usize,
+    pub log_prob: f64,
+    pub acoustic_log_prob: f64, // weighted acoustic part of log_prob
+    pub lm_log_prob: f64, // weighted language model part of log_prob, without word penalties
+}
+
+// word lattice of an utterance.
+// node 0 starts the utterance, and once finished, the last node ends it.
+#[derive(Clone, Debug)]
+pub struct Lattice {
+    pub nodes: Vec<Node>,
+    pub links: Vec<Link>,
+    word_end_nodes: HashMap<(usize, usize), usize>, // by (network node, frame)
+    link_index: HashMap<(usize, usize, usize), usize>, // by (start, end, word)
+}
+
+impl Default for Lattice {
+    fn default() -> Lattice {
+        Lattice {
+            nodes: vec![Node { frame: 0 }],
+            links: Vec::new(),
+            word_end_nodes: HashMap::new(),
+            link_index: HashMap::new(),
+        }
+    }
+}
+
+impl Lattice {
+    pub fn start(&self) -> usize {
+        0
+    }
+
+    pub fn end(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    // node after the word of a network node ending just before the frame, shared by every link of that word end.
+    // network nodes keep their language model context apart.
+    pub fn word_end_node(&mut self, network_node: usize, frame: usize) -> usize {
+        let nodes = &mut self.nodes;
+        *self.word_end_nodes.entry((network_node, frame)).or_insert_with(|| {
+            nodes.push(Node { frame });
+            nodes.len() - 1
+        })
+    }
+
+    // keeps the better of links with the same start, end and word
+    pub fn add_link(&mut self, link: Link) {
+        let key = (link.start, link.end, link.word);
+        match self.link_index.get(&key) {
+            Some(&index) => {
+                if self.links[index].log_prob < link.log_prob {
+                    self.links[index] = link;
+                }
+            },
+            None => {
+                self.link_index.insert(key, self.links.len());
+                self.links.push(link);
+            },
+        }
+    }
+
+    // adds the end node, to which final_links lead, then drops the links
+    // not on a path from the start to the end and the nodes left without links
+    pub fn finish(mut self, n_frames: usize, final_links: &[Link]) -> Lattice {
+        self.nodes.push(Node { frame: n_frames });
+        let end = self.end();
+        for link in final_links.iter() {
+            self.add_link(Link { end, ..*link });
+        }
+
+        let n_nodes = self.nodes.len();
+        let mut reaches_end = vec![false; n_nodes];
+        reaches_end[end] = true;
+        let mut from_start = vec![false; n_nodes];
+        from_start[0] = true;
+        // links only go forward in time, so visiting them by start time propagates reachability
+        let mut order: Vec<usize> = (0..self.links.len()).collect();
+        order.sort_by_key(|&index| (self.nodes[self.links[index].start].frame, self.links[index].start));
+        for &index in order.iter() {
+            let link = &self.links[index];
+            if from_start[link.start] {
+                from_start[link.end] = true;
+            }
+        }
+        for &index in order.iter().rev() {
+            let link = &self.links[index];
+            if reaches_end[link.end] {
+                reaches_end[link.start] = true;
+            }
+        }
+
+        let is_kept = |node: usize| from_start[node] && reaches_end[node];
+        let mut new_index: Vec<Option<usize>> = vec![None; n_nodes];
+        let mut nodes = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if is_kept(index) || index == 0 || index == end {
+                new_index[index] = Some(nodes.len());
+                nodes.push(*node);
+            }
+        }
+        let links = self.links.iter()
+            .filter(|link| is_kept(link.start) && is_kept(link.end))
+            .map(|link| Link {
+                start: new_index[link.start].expect("Kept Node"),
+                end: new_index[link.end].expect("Kept Node"),
+                ..*link
+            })
+            .collect();
+
+        Lattice { nodes, links, word_end_nodes: HashMap::new(), link_index: HashMap::new() }
+    }
+
+    // posterior probability of every link, the share of the start to end paths passing through it.
+    // log probs are multiplied by scale first, which below 1 flattens the posteriors.
+    pub fn link_posteriors(&self, scale: f64) -> Vec<f64> {
+        let mut order: Vec<usize> = (0..self.links.len()).collect();
+        order.sort_by_key(|&index| (self.nodes[self.links[index].start].frame, self.links[index].start));
+
+        let mut forward = vec![f64::NEG_INFINITY; self.nodes.len()];
+        forward[self.start()] = 0f64;
+        for &index in order.iter() {
+            let link = &self.links[index];
+            forward[link.end] = log_add(forward[link.end], forward[link.start] + scale * link.log_prob);
+        }
+        let mut backward = vec![f64::NEG_INFINITY; self.nodes.len()];
+        backward[self.end()] = 0f64;
+        for &index in order.iter().rev() {
+            let link = &self.links[index];
+            backward[link.start] = log_add(backward[link.start], scale * link.log_prob + backward[link.end]);
+        }
+
+        let total = forward[self.end()];
+        self.links.iter()
+            .map(|link| if total == f64::NEG_INFINITY {
+                0f64
+            } else {
+                (forward[link.start] + scale * link.log_prob + backward[link.end] - total).exp()
+            })
+            .collect()
+    }
+}
+
+// log(exp(a) + exp(b))
+fn log_add(a: f64, b: f64) -> f64 {
+    let (high, low) = if a > b { (a, b) } else { (b, a) };
+    if low == f64::NEG_INFINITY {
+        high
+    } else {
+        high + (low - high).exp().ln_1p()
+    }
+}
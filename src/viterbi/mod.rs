@@ -1,175 +1,188 @@
 mod transition;
+mod search;
+mod lattice;
+mod graph;
+mod align;
 
-use constants::*;
-use phone::Phone;
-use word::Word;
-use dnn::Dnn;
-pub use self::transition::{Transitions, wire as wire_transitions};
+use std::collections::HashMap;
 
-#[derive(Clone, Copy, Debug)]
+use word::Word;
+use dnn::{LabelMap, Posteriors};
+use mlf::Label;
+pub use self::transition::{Transition, Transitions, wire as wire_transitions};
+pub use self::search::{Search, Trace, Path, Segment};
+pub use self::lattice::{Lattice, Link, Node};
+pub use self::align::{align, Alignment, AlignmentError, Unit};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct StateRef {
-    pub word: usize,
+    pub node: usize, // of the word network
     pub phone: usize,
     pub state: usize,
 }
 
+// log probability beams relative to the best score of each frame.
+// states below `beam` are not expanded, and only states within `word_end_beam` may leave their word.
+// at most `max_active` best states are expanded at each frame.
 #[derive(Clone, Copy, Debug)]
-pub struct Value {
-    log_prob: f64,
-    prev: Option<StateRef>,
-    word_changed: bool,
+pub struct Pruning {
+    pub beam: f64,
+    pub word_end_beam: f64,
+    pub max_active: usize,
 }
 
-fn consider_and_apply(new_value: Value, old_value: &mut Option<Value>) {
-    let is_new_value_better = 
-        if let Some(value) = old_value {
-            value.log_prob < new_value.log_prob
-        } else {
-            true
-        };
+impl Default for Pruning {
+    fn default() -> Pruning {
+        Pruning { beam: f64::INFINITY, word_end_beam: f64::INFINITY, max_active: usize::MAX }
+    }
+}
 
-    if is_new_value_better {
-        *old_value = Some(new_value);
+// what a run keeps besides the best hypothesis
+#[derive(Clone, Copy, Debug)]
+pub struct Outputs {
+    pub n_best: usize, // hypotheses of distinct word sequences
+    pub keep_lattice: bool,
+    pub confidence_scale: Option<f64>, // multiplies lattice log probs for word confidences, None for no confidences
+    pub keep_frame_stats: bool,
+}
+
+impl Default for Outputs {
+    fn default() -> Outputs {
+        Outputs { n_best: 1, keep_lattice: false, confidence_scale: None, keep_frame_stats: false }
     }
 }
 
-pub fn run<'w>(
-    spectrogram: &[[f64; N_DIMENSION]],
-    phones: &[Phone],
-    words: &'w [Word<'w>],
-    transitions: &Transitions,
-    dnn: &mut Dnn,
-) -> Vec<&'w Word<'w>> {
-    let mut table = init_table(spectrogram.len(), words);
-
-    let spectrum_window = make_spectrum_window(spectrogram, 0, dnn.spectrum_window_range);
-    let observation_prob = dnn.compute_observation_prob(&spectrum_window, phones);
-
-    for t in transitions.from_start.iter() {
-        let dest_value = &mut table[0][t.dest.word][t.dest.phone][t.dest.state];
-        let p_index = words[t.dest.word].phones[t.dest.phone].index;
-        let log_prob = t.log_prob + (observation_prob[p_index][t.dest.state] as f64).ln();
-        consider_and_apply(
-            Value { log_prob, prev: None, word_changed: false },
-            dest_value
-        )
+#[derive(Clone, Copy, Debug)]
+pub struct FrameStats {
+    pub n_active: usize, // states having a token
+    pub n_expanded: usize, // states left after pruning
+    pub best_log_prob: f64,
+}
+
+// a recognized word sequence and its scores
+#[derive(Clone, Debug)]
+pub struct Hypothesis<'w> {
+    pub words: Vec<&'w Word<'w>>,
+    pub segments: Vec<Segment>, // frames and scores of each word
+    pub log_prob: f64,
+    pub acoustic_log_prob: f64, // weighted acoustic part of log_prob
+    pub lm_log_prob: f64, // weighted language model part of log_prob, without word penalties
+    pub confidences: Option<Vec<f64>>, // of each word in [0, 1], when asked for
+}
+
+impl<'w> Hypothesis<'w> {
+    // word names without sentence boundaries
+    pub fn transcript(&self) -> Vec<&'w str> {
+        self.words.iter()
+            .filter(|word| !word.is_sentence_boundary())
+            .map(|word| word.name.as_str())
+            .collect()
     }
 
-    for t in 0..spectrogram.len()-1 {
-        let spectrum_window = make_spectrum_window(spectrogram, t + 1, dnn.spectrum_window_range);
-        let observation_prob = dnn.compute_observation_prob(&spectrum_window, phones);
-
-        for (w, word) in words.iter().enumerate() {
-            for (p, phone) in word.phones.iter().enumerate() {
-                for s in 0..phone.n_states {
-                    let table_value = table[t][w][p][s];
-                    match table_value {
-                        Some(prev_value) => {
-                            for tr in transitions.from_state[w][p][s].iter() {
-                                let next_p_index = words[tr.dest.word].phones[tr.dest.phone].index;
-                                let log_prob = prev_value.log_prob + tr.log_prob + (observation_prob[next_p_index][tr.dest.state] as f64).ln();
-                                consider_and_apply(
-                                    Value { 
-                                        log_prob,
-                                        prev: Some(StateRef { word: w, phone: p, state: s }),
-                                        word_changed: tr.to_next_word
-                                    },
-                                    &mut table[t+1][tr.dest.word][tr.dest.phone][tr.dest.state]
-                                )
-                            }
-                        },
-                        None => ()
-                    }
-                }
-            }
-        }
+    // words with their times, total scores and confidences if any, without sentence boundaries
+    pub fn labels(&self) -> Vec<Label<'w>> {
+        self.words.iter().zip(self.segments.iter()).enumerate()
+            .filter(|(_, (word, _))| !word.is_sentence_boundary())
+            .map(|(i, (word, segment))| Label {
+                confidence: self.confidences.as_ref().map(|confidences| confidences[i]),
+                ..Label::of_frames(&word.name, segment.start_frame, segment.end_frame, segment.log_prob)
+            })
+            .collect()
     }
-    let max_ref = get_max(&table[spectrogram.len() - 1]);
-    let word_index_seq = backtrace(spectrogram.len() - 1, max_ref.word, max_ref.phone, max_ref.state, &table);
+}
 
-    word_index_seq.into_iter()
-        .map(|index| &words[index])
-        .collect()
+#[derive(Debug)]
+pub struct Output<'w> {
+    pub hypotheses: Vec<Hypothesis<'w>>, // distinct word sequences the network lets end, best first
+    pub lattice: Option<Lattice>,
+    pub frame_stats: Vec<FrameStats>, // for every frame but the last, when kept
 }
 
-// backtrace and return word index sequence
-fn backtrace(time: usize, word: usize, phone: usize, state: usize, table: &Vec<Vec<Vec<Vec<Option<Value>>>>>) -> Vec<usize> {
-    let value = &table[time][word][phone][state].expect("No Table Value");
-
-    match value.prev {
-        Some(StateRef { word: prev_word, phone: prev_phone, state: prev_state }) => {
-            let mut seq = backtrace(time - 1, prev_word, prev_phone, prev_state, table);
-            if value.word_changed {
-                seq.push(prev_word);
-            }
-            seq
-        },
-        None => Vec::new()
+// start frame, end frame and posterior of every lattice link, by the name of its word, in link order
+fn link_spans<'w>(lattice: &Lattice, link_posteriors: &[f64], words: &'w [Word]) -> HashMap<&'w str, Vec<(usize, usize, f64)>> {
+    let mut spans: HashMap<&str, Vec<(usize, usize, f64)>> = HashMap::new();
+    for (link, &posterior) in lattice.links.iter().zip(link_posteriors.iter()) {
+        let span = (lattice.nodes[link.start].frame, lattice.nodes[link.end].frame, posterior);
+        spans.entry(&words[link.word].name).or_default().push(span);
     }
+    spans
 }
 
-fn get_max(last_values: &Vec<Vec<Vec<Option<Value>>>>) -> StateRef {
-    let mut max = None;
-    for w in 0..last_values.len() {
-        for p in 0..last_values[w].len() {
-            for s in 0..last_values[w][p].len() {
-                match &last_values[w][p][s] {
-                    Some(value) => {
-                        consider_and_apply(
-                            Value {
-                                log_prob: value.log_prob,
-                                prev: Some(StateRef { word: w, phone: p, state: s }),
-                                word_changed: false,
-                            },
-                            &mut max,
-                        )
-                    },
-                    None => ()
-                }
-            }
-        }
+// highest share, at any frame of the segment, of the lattice paths in a word of the same name.
+// the share only rises where a link starts, so only the first frame and those starts are summed.
+fn confidence(spans: &HashMap<&str, Vec<(usize, usize, f64)>>, words: &[Word], segment: &Segment) -> f64 {
+    let overlapping: Vec<(usize, usize, f64)> = match spans.get(words[segment.word].name.as_str()) {
+        Some(spans) => spans.iter().cloned()
+            .filter(|&(start, end, _)| start <= segment.end_frame && segment.start_frame < end)
+            .collect(),
+        None => return 0f64,
+    };
+    let frames = overlapping.iter().map(|&(start, _, _)| start)
+        .filter(|&start| segment.start_frame < start)
+        .chain(Some(segment.start_frame));
+    let mut best = 0f64;
+    for t in frames {
+        let share: f64 = overlapping.iter()
+            .filter(|&&(start, end, _)| start <= t && t < end)
+            .map(|&(_, _, posterior)| posterior)
+            .sum();
+        best = best.max(share);
     }
-
-    max.expect("Max Value").prev.expect("Max StateRef")
+    best.min(1f64)
 }
 
-fn make_spectrum_window(spectrogram: &[[f64; N_DIMENSION]], index: usize, range: (i32, i32)) -> Vec<f32> {
-    let (start, end) = range;
-    let mut spectrum_window = Vec::new();
-    for delta in start..end {
-        let t = index as i32 + delta;
-        let spectrum =
-            if t < 0 {
-                &spectrogram[0]
-            }
-            else if t >= spectrogram.len() as i32 {
-                &spectrogram[spectrogram.len() - 1]
-            } else {
-                &spectrogram[t as usize]
-            };
-
-        for value in spectrum.iter() {
-            spectrum_window.push(*value as f32);
-        }
+// decodes an utterance from the dnn posteriors of its frames,
+// keeping up to n_best hypotheses of distinct word sequences and the word lattice if asked.
+// with a confidence scale, the words get confidences from the lattice posteriors scaled by it.
+pub fn run<'w>(
+    posteriors: &Posteriors,
+    label_map: &LabelMap,
+    words: &'w [Word<'w>],
+    transitions: &Transitions,
+    pruning: &Pruning,
+    outputs: &Outputs,
+) -> Output<'w> {
+    let mut search = new_search(label_map, words, transitions, pruning, outputs);
+    for t in 0..posteriors.n_frames() {
+        search.step(posteriors.frame(t));
     }
 
-    spectrum_window
+    finish_search(search, words, outputs)
 }
 
-// reset and resize multi-demensional vec values
-fn init_table(time_length: usize, words: &[Word]) -> Vec<Vec<Vec<Vec<Option<Value>>>>> {
-    let mut table = Vec::with_capacity(time_length);
-    for t in 0..time_length {
-        table.push(Vec::with_capacity(words.len()));
-        for (w, word) in words.iter().enumerate() {
-            table[t].push(Vec::with_capacity(word.phones.len()));
-            for (p, phone) in word.phones.iter().enumerate() {
-                table[t][w].push(Vec::with_capacity(phone.n_states));
-                for _ in 0..phone.n_states {
-                    table[t][w][p].push(None);
-                }
-            }
-        }
+// a search keeping what the outputs need, to be fed frames one by one
+pub fn new_search(label_map: &LabelMap, words: &[Word], transitions: &Transitions, pruning: &Pruning, outputs: &Outputs) -> Search {
+    let mut search = Search::new(words, transitions, label_map, *pruning, outputs.n_best);
+    if outputs.keep_lattice || outputs.confidence_scale.is_some() {
+        search.keep_lattice();
+    }
+    if outputs.keep_frame_stats {
+        search.keep_frame_stats();
     }
-    table
-}
\ No newline at end of file
+    search
+}
+
+// hypotheses, lattice and frame stats of a search fed every frame of an utterance
+pub fn finish_search<'w>(search: Search, words: &'w [Word<'w>], outputs: &Outputs) -> Output<'w> {
+    let lattice = search.lattice();
+    let spans = match (lattice.as_ref(), outputs.confidence_scale) {
+        (Some(lattice), Some(scale)) => Some(link_spans(lattice, &lattice.link_posteriors(scale), words)),
+        _ => None,
+    };
+
+    let hypotheses = search.best_paths(outputs.n_best).into_iter()
+        .map(|path| Hypothesis {
+            words: path.segments.iter().map(|segment| &words[segment.word]).collect(),
+            confidences: spans.as_ref().map(|spans| path.segments.iter()
+                .map(|segment| confidence(spans, words, segment))
+                .collect()),
+            segments: path.segments,
+            log_prob: path.log_prob,
+            acoustic_log_prob: path.acoustic_log_prob,
+            lm_log_prob: path.lm_log_prob,
+        })
+        .collect();
+
+    let lattice = if outputs.keep_lattice { lattice } else { None };
+    Output { hypotheses, lattice, frame_stats: search.into_frame_stats() }
+}
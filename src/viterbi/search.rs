@@ -0,0 +1,707 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use constants::TRACEBACK_INTERVAL;
+use word::Word;
+use dnn::LabelMap;
+use viterbi::{FrameStats, Pruning, Transitions};
+use viterbi::graph::Graph;
+use viterbi::lattice::{Lattice, Link};
+
+// word histories interned by the history before the last word and that word,
+// so that equal ids are equal word sequences. id 0 is the empty history.
+#[derive(Debug)]
+struct Histories {
+    ids: HashMap<(usize, usize), usize>,
+    n_ids: usize, // ids given out, which are never given again
+}
+
+impl Default for Histories {
+    fn default() -> Histories {
+        Histories { ids: HashMap::new(), n_ids: 1 }
+    }
+}
+
+impl Histories {
+    // history after leaving a word, which sentence boundaries leave unchanged
+    fn extend(&mut self, history: usize, history_word: Option<usize>) -> usize {
+        let word = match history_word {
+            Some(word) => word,
+            None => return history,
+        };
+        let n_ids = &mut self.n_ids;
+        *self.ids.entry((history, word)).or_insert_with(|| {
+            *n_ids += 1;
+            *n_ids - 1
+        })
+    }
+
+    // forgets the histories nobody holds, which no later token can reach again
+    fn retain(&mut self, held: &HashSet<usize>) {
+        self.ids.retain(|_, id| held.contains(id));
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Token {
+    log_prob: f64,
+    acoustic_log_prob: f64, // parts of log_prob
+    lm_log_prob: f64,
+    history: usize, // id of the word names left, without sentence boundaries, in the history table
+    trace: Option<usize>, // the last word boundary passed
+    left_word: Option<WordEnd>, // word left on the way to this frame, traced once the frame is done
+}
+
+#[derive(Clone, Copy, Debug)]
+struct WordEnd {
+    word: usize,
+    node: usize, // of the word network, which lattice nodes tell apart
+    log_prob: f64, // scores of the token at the last frame of the word
+    acoustic_log_prob: f64,
+    lm_log_prob: f64,
+}
+
+// a word boundary on the path of a token
+#[derive(Clone, Copy, Debug)]
+pub struct Trace {
+    pub word: usize,
+    pub frame: usize, // last frame of the word
+    pub prev: Option<usize>,
+    pub log_prob: f64, // scores of the path up to the end of the word
+    pub acoustic_log_prob: f64,
+    pub lm_log_prob: f64,
+    node: Option<usize>, // lattice node after the word
+    depth: usize, // number of traces before this one
+}
+
+// scores and lattice node of the path before a word
+#[derive(Clone, Copy, Debug, Default)]
+struct Boundary {
+    frame: usize, // frames before the boundary
+    log_prob: f64,
+    acoustic_log_prob: f64,
+    lm_log_prob: f64,
+    node: usize,
+}
+
+// a word on a path and its own scores
+#[derive(Clone, Copy, Debug)]
+pub struct Segment {
+    pub word: usize,
+    pub start_frame: usize,
+    pub end_frame: usize, // last frame of the word
+    pub log_prob: f64,
+    pub acoustic_log_prob: f64,
+    pub lm_log_prob: f64,
+}
+
+// a word sequence reaching the current frame and its scores
+#[derive(Clone, Debug)]
+pub struct Path {
+    pub segments: Vec<Segment>, // without the word it is in
+    pub log_prob: f64,
+    pub acoustic_log_prob: f64, // weighted acoustic part of log_prob
+    pub lm_log_prob: f64, // weighted language model part of log_prob, without word penalties
+}
+
+// token passing Viterbi search which keeps the tokens of the current frame only.
+// every state keeps up to n_best tokens with distinct word histories, best first.
+// every TRACEBACK_INTERVAL frames, the words shared by all active tokens are committed
+// and traces no token refers to are dropped, so memory stays bounded on long input.
+pub struct Search {
+    graph: Graph,
+    pruning: Pruning,
+    n_best: usize,
+    histories: Histories,
+    tokens: Vec<Vec<Token>>, // by state number
+    active: Vec<usize>, // states having a token
+    next_tokens: Vec<Vec<Token>>,
+    traces: Vec<Trace>,
+    committed: Vec<Segment>, // words before every trace
+    root: Boundary, // end of the committed words, where paths without traces start
+    lattice: Option<Lattice>,
+    n_frames: usize,
+    frame_stats: Option<Vec<FrameStats>>, // of every expanded frame, when kept
+}
+
+fn consider_and_apply(new_token: Token, dest: usize, tokens: &mut [Vec<Token>], active: &mut Vec<usize>, n_best: usize) {
+    let state_tokens = &mut tokens[dest];
+    if state_tokens.is_empty() {
+        active.push(dest);
+    }
+
+    // a better token replaces the one of the same history, or else the worst one when full
+    match state_tokens.iter().position(|token| token.history == new_token.history) {
+        Some(index) => {
+            if state_tokens[index].log_prob < new_token.log_prob {
+                state_tokens.remove(index);
+            } else {
+                return;
+            }
+        },
+        None => {
+            if state_tokens.len() == n_best {
+                if state_tokens[n_best - 1].log_prob < new_token.log_prob {
+                    state_tokens.pop();
+                } else {
+                    return;
+                }
+            }
+        },
+    }
+
+    // after the tokens as good, which came first
+    let index = state_tokens.iter()
+        .position(|token| token.log_prob < new_token.log_prob)
+        .unwrap_or(state_tokens.len());
+    state_tokens.insert(index, new_token);
+}
+
+impl Search {
+    pub fn new(words: &[Word], transitions: &Transitions, label_map: &LabelMap, pruning: Pruning, n_best: usize) -> Search {
+        let graph = Graph::new(words, transitions, label_map);
+        let n_states = graph.n_states();
+        Search {
+            graph,
+            pruning,
+            n_best: n_best.max(1),
+            histories: Histories::default(),
+            tokens: vec![Vec::new(); n_states],
+            active: Vec::new(),
+            next_tokens: vec![Vec::new(); n_states],
+            traces: Vec::new(),
+            committed: Vec::new(),
+            root: Boundary::default(),
+            lattice: None,
+            n_frames: 0,
+            frame_stats: None,
+        }
+    }
+
+    // records every word end within the word end beam into a lattice.
+    // called before the first frame.
+    pub fn keep_lattice(&mut self) {
+        self.lattice = Some(Lattice::default());
+    }
+
+    // the lattice so far, ending with the words the active tokens are in
+    pub fn lattice(&self) -> Option<Lattice> {
+        let lattice = self.lattice.as_ref()?;
+
+        let mut final_links = Vec::new();
+        for (state, token) in self.active_tokens(true) {
+            let word_end = WordEnd {
+                word: self.graph.word(state),
+                node: self.graph.node(state),
+                log_prob: token.log_prob,
+                acoustic_log_prob: token.acoustic_log_prob,
+                lm_log_prob: token.lm_log_prob,
+            };
+            final_links.push(word_link(boundary(&self.traces, &self.root, token.trace), &word_end, 0));
+        }
+
+        Some(lattice.clone().finish(self.n_frames, &final_links))
+    }
+
+    // records the active and expanded states of every frame.
+    // called before the first frame.
+    pub fn keep_frame_stats(&mut self) {
+        self.frame_stats = Some(Vec::new());
+    }
+
+    // consumes the dnn posteriors of the next frame
+    pub fn step(&mut self, posteriors: &[f32]) {
+        if self.n_frames == 0 {
+            self.start(posteriors);
+        } else {
+            self.expand(posteriors);
+        }
+        self.n_frames += 1;
+
+        if self.n_frames.is_multiple_of(TRACEBACK_INTERVAL) {
+            self.collect_traces();
+        }
+    }
+
+    pub fn n_frames(&self) -> usize {
+        self.n_frames
+    }
+
+    // frame stats if they were kept, or else none
+    pub fn into_frame_stats(self) -> Vec<FrameStats> {
+        self.frame_stats.unwrap_or_default()
+    }
+
+    // words which no later frame can change
+    pub fn committed(&self) -> &[Segment] {
+        &self.committed
+    }
+
+    // active tokens, or only those the utterance may end with, in final nodes of the network
+    fn active_tokens(&self, only_final: bool) -> Vec<(usize, &Token)> {
+        self.active.iter()
+            .filter(|&&state| !only_final || self.graph.is_final(state))
+            .flat_map(|&state| self.tokens[state].iter().map(move |token| (state, token)))
+            .collect()
+    }
+
+    // paths of the n best tokens with distinct word histories, best first,
+    // among the tokens the utterance may end with. none when pruning left no such token.
+    pub fn best_paths(&self, n: usize) -> Vec<Path> {
+        self.paths(self.active_tokens(true), n)
+    }
+
+    // path of the best token wherever it is, as a guess at the words so far before the utterance ends
+    pub fn best_partial_path(&self) -> Option<Path> {
+        self.paths(self.active_tokens(false), 1).into_iter().next()
+    }
+
+    // ties go to the token at the lower state
+    fn paths(&self, mut candidates: Vec<(usize, &Token)>, n: usize) -> Vec<Path> {
+        candidates.sort_by(|&(a_state, a), &(b_state, b)|
+            b.log_prob.partial_cmp(&a.log_prob).expect("Log Prob").then(a_state.cmp(&b_state)));
+
+        let mut histories = HashSet::new();
+        candidates.into_iter()
+            .filter(|(_, token)| histories.insert(token.history))
+            .take(n)
+            .map(|(_, token)| {
+                let mut segments = self.committed.clone();
+                segments.extend(self.backtrace(token.trace));
+                Path {
+                    segments,
+                    log_prob: token.log_prob,
+                    acoustic_log_prob: token.acoustic_log_prob,
+                    lm_log_prob: token.lm_log_prob,
+                }
+            })
+            .collect()
+    }
+
+    // partial traceback: commits the words up to the latest trace shared by all active tokens,
+    // then keeps only the traces after it and the histories which some token still refers to.
+    fn collect_traces(&mut self) {
+        let mut common: Option<Option<usize>> = None;
+        for &state in self.active.iter() {
+            for token in self.tokens[state].iter() {
+                common = Some(match common {
+                    None => token.trace,
+                    Some(common) => self.common_ancestor(common, token.trace),
+                });
+            }
+        }
+        let common = common.and_then(|common| common);
+
+        let committed = self.backtrace(common);
+        self.committed.extend(committed);
+        self.root = boundary(&self.traces, &self.root, common);
+
+        let mut is_kept = vec![false; self.traces.len()];
+        for &state in self.active.iter() {
+            for token in self.tokens[state].iter() {
+                let mut trace = token.trace;
+                while let Some(index) = trace {
+                    if Some(index) == common || is_kept[index] {
+                        break;
+                    }
+                    is_kept[index] = true;
+                    trace = self.traces[index].prev;
+                }
+            }
+        }
+
+        // traces only refer to earlier ones, so they are renumbered in order
+        let mut new_index: Vec<Option<usize>> = vec![None; self.traces.len()];
+        let mut traces: Vec<Trace> = Vec::with_capacity(is_kept.iter().filter(|&&kept| kept).count());
+        for (index, trace) in self.traces.iter().enumerate() {
+            if !is_kept[index] {
+                continue;
+            }
+            let prev = trace.prev.and_then(|prev| new_index[prev]);
+            let depth = prev.map(|prev| traces[prev].depth + 1).unwrap_or(0);
+            new_index[index] = Some(traces.len());
+            traces.push(Trace { prev, depth, ..*trace });
+        }
+        self.traces = traces;
+
+        for &state in self.active.iter() {
+            for token in self.tokens[state].iter_mut() {
+                token.trace = token.trace.and_then(|index| new_index[index]);
+            }
+        }
+
+        let held = self.active.iter()
+            .flat_map(|&state| self.tokens[state].iter().map(|token| token.history))
+            .collect();
+        self.histories.retain(&held);
+    }
+
+    fn common_ancestor(&self, mut a: Option<usize>, mut b: Option<usize>) -> Option<usize> {
+        while let (Some(x), Some(y)) = (a, b) {
+            if x == y {
+                return a;
+            }
+            let (x_depth, y_depth) = (self.traces[x].depth, self.traces[y].depth);
+            if x_depth >= y_depth {
+                a = self.traces[x].prev;
+            }
+            if y_depth >= x_depth {
+                b = self.traces[y].prev;
+            }
+        }
+        None
+    }
+
+    fn backtrace(&self, mut trace: Option<usize>) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        while let Some(index) = trace {
+            let end = &self.traces[index];
+            let start = boundary(&self.traces, &self.root, end.prev);
+            segments.push(Segment {
+                word: end.word,
+                start_frame: start.frame,
+                end_frame: end.frame,
+                log_prob: end.log_prob - start.log_prob,
+                acoustic_log_prob: end.acoustic_log_prob - start.acoustic_log_prob,
+                lm_log_prob: end.lm_log_prob - start.lm_log_prob,
+            });
+            trace = end.prev;
+        }
+        segments.reverse();
+        segments
+    }
+
+    fn start(&mut self, posteriors: &[f32]) {
+        for arc in self.graph.from_start.iter() {
+            let acoustic_log_prob = self.graph.observation_log_prob(posteriors, arc.dest);
+            consider_and_apply(
+                Token {
+                    log_prob: arc.log_prob + acoustic_log_prob,
+                    acoustic_log_prob,
+                    lm_log_prob: arc.lm_log_prob,
+                    history: 0,
+                    trace: None,
+                    left_word: None,
+                },
+                arc.dest,
+                &mut self.tokens,
+                &mut self.active,
+                self.n_best,
+            );
+        }
+    }
+
+    fn expand(&mut self, posteriors: &[f32]) {
+        let n_active = self.active.len();
+        let best_log_prob = self.active.iter()
+            .map(|&state| self.tokens[state][0].log_prob)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let threshold = best_log_prob - self.pruning.beam;
+        let word_end_threshold = best_log_prob - self.pruning.word_end_beam;
+
+        {
+            let tokens = &mut self.tokens;
+            self.active.retain(|&state| {
+                tokens[state].retain(|token| token.log_prob >= threshold);
+                !tokens[state].is_empty()
+            });
+        }
+        if self.active.len() > self.pruning.max_active {
+            // keep the best states, preferring earlier ones on ties
+            let tokens = &mut self.tokens;
+            self.active.sort_by(|&a, &b| {
+                let (a_log_prob, b_log_prob) = (tokens[a][0].log_prob, tokens[b][0].log_prob);
+                b_log_prob.partial_cmp(&a_log_prob).expect("Log Prob").then(a.cmp(&b))
+            });
+            for &state in self.active[self.pruning.max_active..].iter() {
+                tokens[state].clear();
+            }
+            self.active.truncate(self.pruning.max_active);
+        }
+
+        if let Some(frame_stats) = self.frame_stats.as_mut() {
+            frame_stats.push(FrameStats { n_active, n_expanded: self.active.len(), best_log_prob });
+        }
+
+        // expand states in (word, phone, state) order so that ties are broken alike
+        self.active.sort();
+
+        let mut next_active = Vec::with_capacity(self.active.len());
+        let last_frame = self.n_frames - 1;
+        for &source in self.active.iter() {
+            let word = self.graph.word(source);
+            let node = self.graph.node(source);
+            let ends_word = self.graph.from_state[source].iter().any(|arc| arc.to_next_word);
+            for rank in 0..self.tokens[source].len() {
+                let token = self.tokens[source][rank];
+                let can_leave_word = token.log_prob >= word_end_threshold;
+                let next_word_history = if ends_word && can_leave_word {
+                    self.histories.extend(token.history, self.graph.history_word(word))
+                } else {
+                    token.history
+                };
+                let word_end = WordEnd {
+                    word,
+                    node,
+                    log_prob: token.log_prob,
+                    acoustic_log_prob: token.acoustic_log_prob,
+                    lm_log_prob: token.lm_log_prob,
+                };
+                if let Some(lattice) = self.lattice.as_mut() {
+                    if ends_word && can_leave_word {
+                        let end = lattice.word_end_node(node, last_frame + 1);
+                        lattice.add_link(word_link(boundary(&self.traces, &self.root, token.trace), &word_end, end));
+                    }
+                }
+
+                for arc in self.graph.from_state[source].iter() {
+                    if arc.to_next_word && !can_leave_word {
+                        continue;
+                    }
+
+                    let observation_log_prob = self.graph.observation_log_prob(posteriors, arc.dest);
+                    consider_and_apply(
+                        Token {
+                            log_prob: token.log_prob + arc.log_prob + observation_log_prob,
+                            acoustic_log_prob: token.acoustic_log_prob + observation_log_prob,
+                            lm_log_prob: token.lm_log_prob + arc.lm_log_prob,
+                            history: if arc.to_next_word { next_word_history } else { token.history },
+                            trace: token.trace,
+                            left_word: if arc.to_next_word { Some(word_end) } else { None },
+                        },
+                        arc.dest,
+                        &mut self.next_tokens,
+                        &mut next_active,
+                        self.n_best,
+                    );
+                }
+            }
+        }
+
+        for &state in self.active.iter() {
+            self.tokens[state].clear();
+        }
+        mem::swap(&mut self.tokens, &mut self.next_tokens);
+        self.active = next_active;
+
+        // trace the words left between the previous frame and this one
+        let traces = &mut self.traces;
+        for &state in self.active.iter() {
+            for token in self.tokens[state].iter_mut() {
+                if let Some(word_end) = token.left_word.take() {
+                    let depth = token.trace.map(|prev| traces[prev].depth + 1).unwrap_or(0);
+                    let node = self.lattice.as_mut().map(|lattice| lattice.word_end_node(word_end.node, last_frame + 1));
+                    traces.push(Trace {
+                        word: word_end.word,
+                        frame: last_frame,
+                        prev: token.trace,
+                        log_prob: word_end.log_prob,
+                        acoustic_log_prob: word_end.acoustic_log_prob,
+                        lm_log_prob: word_end.lm_log_prob,
+                        node,
+                        depth,
+                    });
+                    token.trace = Some(traces.len() - 1);
+                }
+            }
+        }
+    }
+}
+
+// boundary after a trace, or the root for paths without traces
+fn boundary(traces: &[Trace], root: &Boundary, trace: Option<usize>) -> Boundary {
+    match trace {
+        Some(index) => {
+            let trace = &traces[index];
+            Boundary {
+                frame: trace.frame + 1,
+                log_prob: trace.log_prob,
+                acoustic_log_prob: trace.acoustic_log_prob,
+                lm_log_prob: trace.lm_log_prob,
+                node: trace.node.unwrap_or(0),
+            }
+        },
+        None => *root,
+    }
+}
+
+// lattice link of a word from start to end, scored by the difference
+fn word_link(start: Boundary, word_end: &WordEnd, end: usize) -> Link {
+    Link {
+        start: start.node,
+        end,
+        word: word_end.word,
+        log_prob: word_end.log_prob - start.log_prob,
+        acoustic_log_prob: word_end.acoustic_log_prob - start.acoustic_log_prob,
+        lm_log_prob: word_end.lm_log_prob - start.lm_log_prob,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network;
+    use params::Params;
+    use phone::Phone;
+    use viterbi::wire_transitions;
+
+    // left to right hmm whose probabilities are all halves, so that many paths tie
+    fn phone(index: usize, name: &str, n_states: usize) -> Phone {
+        let mut trans_prob = vec![vec![0f64; n_states]; n_states];
+        for s in 0..n_states {
+            trans_prob[s][s] = 0.5;
+            if s + 1 < n_states {
+                trans_prob[s][s + 1] = 0.5;
+            }
+        }
+        let mut out_prob = vec![0f64; n_states];
+        out_prob[n_states - 1] = 0.5;
+        let mut in_prob = vec![0f64; n_states];
+        in_prob[0] = 1f64;
+        Phone { index, name: name.to_string(), n_states, in_prob, trans_prob, out_prob, skip_prob: 0.5 }
+    }
+
+    fn words(phones: &[Phone]) -> Vec<Word<'_>> {
+        let prons: &[(&str, &[usize])] = &[("<s>", &[0]), ("one", &[1, 2, 4]), ("two", &[3, 2, 4]), ("oh", &[2, 4])];
+        prons.iter()
+            .map(|&(name, indices)| Word {
+                name: name.to_string(),
+                phones: indices.iter().map(|&index| &phones[index]).collect(),
+                head_prob: 0.25,
+                next_word_prob: vec![0.25; prons.len()],
+            })
+            .collect()
+    }
+
+    // every state of every phone has its own label
+    fn label_map(phones: &[Phone]) -> LabelMap {
+        let mut state_labels = Vec::new();
+        let mut n_labels = 0;
+        for phone in phones.iter() {
+            state_labels.push((n_labels..n_labels + phone.n_states).map(Some).collect());
+            n_labels += phone.n_states;
+        }
+        LabelMap { state_labels, log_offsets: vec![0f64; n_labels], acoustic_scale: 1f64 }
+    }
+
+    // posteriors of a few distinct values, so that paths often score the same
+    fn posteriors(n_frames: usize, n_labels: usize) -> Vec<Vec<f32>> {
+        let mut seed = 12345u64;
+        (0..n_frames)
+            .map(|_| (0..n_labels)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                    [0.125f32, 0.25, 0.5][(seed >> 33) as usize % 3]
+                })
+                .collect())
+            .collect()
+    }
+
+    #[derive(Clone, Copy)]
+    struct Cell {
+        log_prob: f64,
+        prev: Option<usize>,
+        word_changed: bool,
+    }
+
+    // the search this one replaced: a table of the best predecessor of every state at every frame,
+    // traced back from the best state of the last frame. returns the words left and the frame each ends at.
+    fn full_table_search(graph: &Graph, posteriors: &[Vec<f32>]) -> (Vec<(usize, usize)>, f64) {
+        let consider = |cell: Cell, old: &mut Option<Cell>| {
+            if old.is_none_or(|old| old.log_prob < cell.log_prob) {
+                *old = Some(cell);
+            }
+        };
+
+        let mut table: Vec<Vec<Option<Cell>>> = vec![vec![None; graph.n_states()]; posteriors.len()];
+        for arc in graph.from_start.iter() {
+            let log_prob = arc.log_prob + graph.observation_log_prob(&posteriors[0], arc.dest);
+            consider(Cell { log_prob, prev: None, word_changed: false }, &mut table[0][arc.dest]);
+        }
+        for t in 0..posteriors.len() - 1 {
+            for state in 0..graph.n_states() {
+                if let Some(cell) = table[t][state] {
+                    for arc in graph.from_state[state].iter() {
+                        let log_prob = cell.log_prob + arc.log_prob + graph.observation_log_prob(&posteriors[t + 1], arc.dest);
+                        consider(Cell { log_prob, prev: Some(state), word_changed: arc.to_next_word }, &mut table[t + 1][arc.dest]);
+                    }
+                }
+            }
+        }
+
+        let mut best: Option<(usize, f64)> = None;
+        for (state, cell) in table[posteriors.len() - 1].iter().enumerate() {
+            if let Some(cell) = cell {
+                if best.is_none_or(|(_, log_prob)| log_prob < cell.log_prob) {
+                    best = Some((state, cell.log_prob));
+                }
+            }
+        }
+        let (mut state, log_prob) = best.expect("Max Value");
+
+        let mut word_ends = Vec::new();
+        for t in (1..posteriors.len()).rev() {
+            let cell = table[t][state].expect("Table Value");
+            let prev = cell.prev.expect("Prev State");
+            if cell.word_changed {
+                word_ends.push((graph.word(prev), t - 1));
+            }
+            state = prev;
+        }
+        word_ends.reverse();
+        (word_ends, log_prob)
+    }
+
+    fn compare(n_frames: usize, word_penalty: f64) {
+        let phones = vec![phone(0, "sil", 3), phone(1, "w", 2), phone(2, "ah", 3), phone(3, "t", 2), phone(4, "sp", 1)];
+        let words = words(&phones);
+        let label_map = label_map(&phones);
+        let n_labels = label_map.log_offsets.len();
+        let params = Params { word_penalty, ..Params::default() };
+        let transitions = wire_transitions(&words, &network::bigram(&words), &params);
+        let posteriors = posteriors(n_frames, n_labels);
+
+        let mut search = Search::new(&words, &transitions, &label_map, Pruning::default(), 1);
+        for frame in posteriors.iter() {
+            search.step(frame);
+        }
+        let path = search.best_paths(1).into_iter().next().expect("Best Path");
+        let word_ends: Vec<(usize, usize)> = path.segments.iter().map(|segment| (segment.word, segment.end_frame)).collect();
+
+        let graph = Graph::new(&words, &transitions, &label_map);
+        let (expected_word_ends, expected_log_prob) = full_table_search(&graph, &posteriors);
+        assert!(!expected_word_ends.is_empty());
+        assert_eq!(word_ends, expected_word_ends);
+        assert_eq!(path.log_prob, expected_log_prob);
+    }
+
+    #[test]
+    fn token_passing_matches_full_table() {
+        compare(40, 0f64);
+    }
+
+    #[test]
+    fn token_passing_matches_full_table_past_partial_traceback() {
+        compare(3 * TRACEBACK_INTERVAL + 17, 0f64);
+        compare(3 * TRACEBACK_INTERVAL + 17, 2f64);
+    }
+
+    #[test]
+    fn histories_are_equal_only_for_equal_word_sequences() {
+        let mut histories = Histories::default();
+        let one = histories.extend(0, Some(1));
+        let one_two = histories.extend(one, Some(2));
+        let two = histories.extend(0, Some(2));
+        let two_one = histories.extend(two, Some(1));
+        let ids = [0, one, one_two, two, two_one];
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+
+        assert_eq!(histories.extend(one, Some(2)), one_two);
+        assert_eq!(histories.extend(one_two, None), one_two);
+
+        // ids of forgotten histories are not given again
+        histories.retain(&[one].iter().cloned().collect());
+        assert_eq!(histories.extend(0, Some(1)), one);
+        assert!(!ids.contains(&histories.extend(one, Some(2))));
+    }
+}
@@ -1,10 +1,12 @@
-use constants::*;
 use word::Word;
+use network::Network;
+use params::Params;
 use viterbi::StateRef;
 
 #[derive(Debug)]
 pub struct Transition {
     pub log_prob: f64,
+    pub lm_log_prob: f64, // weighted language model part of log_prob
     pub dest: StateRef,
     pub to_next_word: bool,
 }
@@ -12,21 +14,36 @@ pub struct Transition {
 #[derive(Debug)]
 pub struct Transitions {
     pub from_start: Vec<Transition>,
-    pub from_state: Vec<Vec<Vec<Vec<Transition>>>>, // from_state[word][phone][state] has own transitions
+    pub from_state: Vec<Vec<Vec<Vec<Transition>>>>, // from_state[node][phone][state] has own transitions
+    pub words: Vec<usize>, // word of each network node
+    pub is_final: Vec<bool>, // whether the utterance may end in each network node
 }
 
-pub fn wire(words: &[Word]) -> Transitions {
+// log prob of a transition into a word and its language model part weighted by the lm scale
+fn word_log_prob(prob: f64, lm_prob: f64, params: &Params) -> Option<(f64, f64)> {
+    if prob > 0f64 && lm_prob > 0f64 {
+        let lm_log_prob = params.lm_scale * lm_prob.ln();
+        Some((prob.ln() + lm_log_prob, lm_log_prob))
+    } else {
+        None
+    }
+}
+
+// transitions between the hmm states of every network node, each pronounced as its word
+pub fn wire(words: &[Word], network: &Network, params: &Params) -> Transitions {
+    let node_words: Vec<&Word> = network.nodes.iter().map(|node| &words[node.word]).collect();
+
     let mut from_start: Vec<Transition> = Vec::new();
-    for (w, word) in words.iter().enumerate() {
+    for (w, (word, node)) in node_words.iter().zip(network.nodes.iter()).enumerate() {
         let phone = word.phones[0];
         for s in 0..phone.n_states {
-            let prob = word.head_prob * phone.in_prob[s];
-            if prob > 0f64 {
+            if let Some((log_prob, lm_log_prob)) = word_log_prob(phone.in_prob[s], node.head_prob, params) {
                 from_start.push(
                     Transition {
-                        log_prob: prob.ln(),
+                        log_prob,
+                        lm_log_prob,
                         dest: StateRef {
-                            word: w,
+                            node: w,
                             phone: 0,
                             state: s,
                         },
@@ -39,7 +56,7 @@ pub fn wire(words: &[Word]) -> Transitions {
 
     // initialize from_state
     let mut from_state: Vec<Vec<Vec<Vec<Transition>>>> = Vec::new();
-    for (w, word) in words.iter().enumerate() {
+    for (w, word) in node_words.iter().enumerate() {
         from_state.push(Vec::with_capacity(word.phones.len()));
         for (p, phone) in word.phones.iter().enumerate() {
             from_state[w].push(Vec::with_capacity(phone.n_states));
@@ -49,7 +66,7 @@ pub fn wire(words: &[Word]) -> Transitions {
         }
     }
 
-    for (w, word) in words.iter().enumerate() {
+    for (w, word) in node_words.iter().enumerate() {
         for (p, phone) in word.phones.iter().enumerate() {
             // transitions in each phone's hmm
             for s in 0..phone.n_states {
@@ -59,8 +76,9 @@ pub fn wire(words: &[Word]) -> Transitions {
                         from_state[w][p][s].push(
                             Transition {
                                 log_prob: prob.ln(),
+                                lm_log_prob: 0f64,
                                 dest: StateRef {
-                                    word: w,
+                                    node: w,
                                     phone: p,
                                     state: d,
                                 },
@@ -81,8 +99,9 @@ pub fn wire(words: &[Word]) -> Transitions {
                             from_state[w][p][s].push(
                                 Transition {
                                     log_prob: prob.ln(),
+                                    lm_log_prob: 0f64,
                                     dest: StateRef { 
-                                        word: w,
+                                        node: w,
                                         phone: p + 1,
                                         state: d,
                                     },
@@ -97,21 +116,22 @@ pub fn wire(words: &[Word]) -> Transitions {
     }
 
     // transitions to next word
-    for (w, word) in words.iter().enumerate() {
+    for (w, (word, node)) in node_words.iter().zip(network.nodes.iter()).enumerate() {
         let p = word.phones.len() - 1;
         let phone = word.phones[p];
         let is_phone_sp = phone.name == "sp";
-        for (next_w, next_word) in words.iter().enumerate() {
-            let next_phone = next_word.phones[0];
+        for &(next_w, next_word_prob) in node.next.iter() {
+            let next_phone = node_words[next_w].phones[0];
             for d in 0..next_phone.n_states {
                 for s in 0..phone.n_states {
-                    let prob = phone.out_prob[s] * word.next_word_prob[next_w] * next_phone.in_prob[d];
-                    if prob > 0f64 {
+                    let prob = phone.out_prob[s] * next_phone.in_prob[d];
+                    if let Some((log_prob, lm_log_prob)) = word_log_prob(prob, next_word_prob, params) {
                         from_state[w][p][s].push(
                             Transition {
-                                log_prob: prob.ln() - WORD_PENALTY,
+                                log_prob: log_prob - params.word_penalty,
+                                lm_log_prob,
                                 dest: StateRef {
-                                    word: next_w,
+                                    node: next_w,
                                     phone: 0,
                                     state: d,
                                 },
@@ -125,13 +145,14 @@ pub fn wire(words: &[Word]) -> Transitions {
                     // if the phone is "sp", then we can skip it
                     let prev_phone = word.phones[p - 1];
                     for s in 0..prev_phone.n_states {
-                        let prob = prev_phone.out_prob[s] * phone.skip_prob * word.next_word_prob[next_w] * next_phone.in_prob[d];
-                        if prob > 0f64 {
+                        let prob = prev_phone.out_prob[s] * phone.skip_prob * next_phone.in_prob[d];
+                        if let Some((log_prob, lm_log_prob)) = word_log_prob(prob, next_word_prob, params) {
                             from_state[w][p - 1][s].push(
                                 Transition {
-                                    log_prob: prob.ln() - WORD_PENALTY,
+                                    log_prob: log_prob - params.word_penalty,
+                                    lm_log_prob,
                                     dest: StateRef {
-                                        word: next_w,
+                                        node: next_w,
                                         phone: 0,
                                         state: d,
                                     },
@@ -147,6 +168,8 @@ pub fn wire(words: &[Word]) -> Transitions {
 
     Transitions {
         from_start,
-        from_state
+        from_state,
+        words: network.nodes.iter().map(|node| node.word).collect(),
+        is_final: network.nodes.iter().map(|node| node.is_final).collect(),
     }
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
 pub mod read;
 
 pub use self::read::read_words as read_all;
+pub use self::read::read_dictionary;
 
+use constants::{SENTENCE_BOUNDARY, SILENCE_PHONES};
 use phone::Phone;
 
 #[derive(Debug)]
@@ -11,3 +13,14 @@ pub struct Word<'p> {
     pub head_prob: f64,
     pub next_word_prob: Vec<f64>,
 }
+
+impl<'p> Word<'p> {
+    pub fn is_sentence_boundary(&self) -> bool {
+        self.name == SENTENCE_BOUNDARY
+    }
+
+    // pronounced with silence phones only
+    pub fn is_silence(&self) -> bool {
+        self.phones.iter().all(|phone| SILENCE_PHONES.contains(&phone.name.as_str()))
+    }
+}
@@ -1,5 +1,6 @@
 use std::ffi::OsStr;
 
+use error::{Error, Result};
 use fileutil;
 use word::*;
 use phone::{self, Phone};
@@ -9,29 +10,37 @@ pub fn read_words<'p>(
     unigram_path: &str,
     bigram_path: &str,
     phones: &'p [Phone],
-) -> Vec<Word<'p>> {
-    let mut words = read_dictionary(dictionary_path, phones);
-    read_head_probs(unigram_path, &mut words);
-    read_next_word_probs(bigram_path, &mut words);
+) -> Result<Vec<Word<'p>>> {
+    let mut words = read_dictionary(dictionary_path, phones)?;
+    read_head_probs(unigram_path, &mut words)?;
+    read_next_word_probs(bigram_path, &mut words)?;
 
-    words
+    Ok(words)
 }
 
-fn read_dictionary<'p>(path: &str, phones: &'p [Phone]) -> Vec<Word<'p>> {
-    let lines = fileutil::read_lines(OsStr::new(path));
+// words with zero head and next word probabilities
+pub fn read_dictionary<'p>(path: &str, phones: &'p [Phone]) -> Result<Vec<Word<'p>>> {
+    let lines = fileutil::read_lines(OsStr::new(path))?;
 
     let mut words = Vec::new();
-    for line in lines.iter() {
+    for (number, line) in lines.iter().enumerate() {
         let elements: Vec<&str> = line.split_whitespace().collect();
 
-        if elements.len() == 0 {
+        if elements.is_empty() {
             continue;
         }
+        if elements.len() == 1 {
+            return Err(Error::parse(path, number + 1, "", "pronunciation"));
+        }
 
         let name = elements[0].to_string();
-        let phones: Vec<_> = elements[1..].iter()
-            .map(|name| phone::find(name, phones))
-            .collect();
+        let phones = elements[1..].iter()
+            .map(|name| phone::find(name, phones).ok_or_else(|| Error::UnknownPhone {
+                path: path.to_string(),
+                line: number + 1,
+                name: name.to_string(),
+            }))
+            .collect::<Result<Vec<_>>>()?;
 
         words.push(
             Word { name, phones, head_prob: 0f64, next_word_prob: Vec::new() }
@@ -44,38 +53,45 @@ fn read_dictionary<'p>(path: &str, phones: &'p [Phone]) -> Vec<Word<'p>> {
         word.next_word_prob.resize(n_words, 0f64);
     }
 
-    words
+    Ok(words)
+}
+
+fn parse_prob(path: &str, line: usize, prob_str: &str) -> Result<f64> {
+    prob_str.parse().map_err(|_| Error::parse(path, line, prob_str, "probability"))
 }
 
-fn read_head_probs(unigram_path: &str, words: &mut Vec<Word>) {
-    let lines = fileutil::read_lines(OsStr::new(unigram_path));
+fn read_head_probs(unigram_path: &str, words: &mut [Word]) -> Result<()> {
+    let lines = fileutil::read_lines(OsStr::new(unigram_path))?;
 
-    for line in lines.iter() {
+    for (number, line) in lines.iter().enumerate() {
         let mut elements = line.split_whitespace();
 
         match (elements.next(), elements.next()) {
             (Some(word_name), Some(prob_str)) => {
-                let prob: f64 = prob_str.parse().expect("Word Head Prob");
+                let prob = parse_prob(unigram_path, number + 1, prob_str)?;
                 for word in words.iter_mut() {
                     if word.name == word_name {
                         word.head_prob = prob;
                     }
                 }
             },
-            _ => panic!("Invalid Unigram Input: {:?}", line)
+            (None, _) => (),
+            (Some(_), None) => return Err(Error::parse(unigram_path, number + 1, line, "word and probability")),
         }
     }
+
+    Ok(())
 }
 
-fn read_next_word_probs(bigram_path: &str, words: &mut Vec<Word>) {
-    let lines = fileutil::read_lines(OsStr::new(bigram_path));
+fn read_next_word_probs(bigram_path: &str, words: &mut [Word]) -> Result<()> {
+    let lines = fileutil::read_lines(OsStr::new(bigram_path))?;
 
-    for line in lines.iter() {
+    for (number, line) in lines.iter().enumerate() {
         let mut elements = line.split_whitespace();
 
         match (elements.next(), elements.next(), elements.next()) {
             (Some(first), Some(second), Some(prob_str)) => {
-                let prob: f64 = prob_str.parse().expect("Word Transition Prob");
+                let prob = parse_prob(bigram_path, number + 1, prob_str)?;
                 for source in 0..words.len() {
                     if words[source].name != first {
                         continue;
@@ -87,7 +103,10 @@ fn read_next_word_probs(bigram_path: &str, words: &mut Vec<Word>) {
                     }
                 }
             },
-            _ => panic!("Invalid Bigram Input: {:?}", line)
+            (None, _, _) => (),
+            _ => return Err(Error::parse(bigram_path, number + 1, line, "two words and probability")),
         }
     }
+
+    Ok(())
 }